@@ -1,4 +1,6 @@
+use blue_noise::jfa_cpu::jfa_parallel;
 use blue_noise::jfa_wgpu::run;
+use blue_noise::metric::Metric;
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
 pub fn criterion_benchmark(c: &mut Criterion) {
@@ -6,9 +8,30 @@ pub fn criterion_benchmark(c: &mut Criterion) {
 
     let mut group = c.benchmark_group("jfa");
     group.sample_size(10);
-    //group.bench_function("jfa_cpu", |b| b.iter(|| jfa(black_box(&points), black_box((10.,10.)))));
+    //group.bench_function("jfa_cpu", |b| b.iter(|| jfa(black_box(&points), black_box((10.,10.)), black_box((512, 512)))));
     group.bench_function("jfa_gpu", |b| {
-        b.iter(|| run(black_box(&points), black_box((10., 10.))))
+        b.iter(|| {
+            run(
+                black_box(&points),
+                black_box(None),
+                black_box((10., 10.)),
+                black_box((512, 512)),
+                black_box(Metric::Euclidean),
+                black_box(false),
+            )
+        })
+    });
+    group.bench_function("jfa_cpu_parallel", |b| {
+        b.iter(|| {
+            jfa_parallel(
+                black_box(&points),
+                black_box(None),
+                black_box((10., 10.)),
+                black_box((512, 512)),
+                black_box(Metric::Euclidean),
+                black_box(false),
+            )
+        })
     });
     group.finish();
 }
@@ -0,0 +1,164 @@
+// Accuracy of a label grid against the true nearest seed of each pixel center.
+
+use std::fmt;
+
+use rayon::prelude::*;
+
+use crate::metric::Metric;
+use crate::voronoi::SeedGrid;
+
+/// Mislabeled pixels of a label grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccuracyReport {
+    pub pixels: usize,
+    /// Pixels whose seed isn't the nearest one, unlabeled pixels included
+    pub errors: usize,
+    pub error_fraction: f64,
+    /// Largest difference, in domain units, between the distance to the labeled seed and the
+    /// distance to the nearest seed
+    pub max_distance_excess: f64,
+}
+
+impl fmt::Display for AccuracyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} mislabeled pixels out of {} ({:.4}%), worst distance excess {:.6}",
+            self.errors,
+            self.pixels,
+            self.error_fraction * 100.0,
+            self.max_distance_excess
+        )
+    }
+}
+
+/// Seeds bucketed on a grid for nearest neighbor queries.
+struct NearestSeed<'a> {
+    points: &'a [(f64, f64)],
+    metric: Metric,
+    grid: SeedGrid,
+}
+
+impl<'a> NearestSeed<'a> {
+    fn new(points: &'a [(f64, f64)], config: (f64, f64), metric: Metric) -> Self {
+        NearestSeed {
+            points,
+            metric,
+            grid: SeedGrid::new(points, config),
+        }
+    }
+
+    fn distance(&self, seed: (f64, f64), p: (f64, f64)) -> f64 {
+        self.metric.distance(seed.0 - p.0, seed.1 - p.1)
+    }
+
+    /// Returns the distance from `p` to its nearest seed.
+    fn nearest_distance(&self, p: (f64, f64)) -> f64 {
+        let bucket = self.grid.bucket_of(p);
+        let mut best = f64::MAX;
+        let lower_bound = self.metric.euclidean_lower_bound();
+
+        for ring in 0..self.grid.num_rings() {
+            // Seeds in this ring and past it are at least `(ring - 1) * bucket_size` away
+            if ring > 1 && (ring - 1) as f64 * self.grid.bucket_size * lower_bound > best {
+                break;
+            }
+            for i in self.grid.ring(bucket, ring) {
+                best = best.min(self.distance(self.points[i], p));
+            }
+        }
+
+        best
+    }
+}
+
+/// Compares a label grid, as returned by `generate_cells`, to the nearest seed of each pixel
+/// center for the `metric` used to generate it.
+pub fn accuracy(
+    pixels: &[usize],
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    reso: (usize, usize),
+    metric: Metric,
+) -> Result<AccuracyReport, &'static str> {
+    let (reso_x, reso_y) = reso;
+    if pixels.len() != reso_x * reso_y {
+        return Err("Label grid size doesn't match the resolution");
+    }
+    if points.is_empty() {
+        return Err("No points to compare the label grid to");
+    }
+    let pixel_size = (config.0 / reso_x as f64, config.1 / reso_y as f64);
+    let seeds = NearestSeed::new(points, config, metric);
+
+    let (errors, max_distance_excess) = pixels
+        .par_chunks(reso_x)
+        .enumerate()
+        .map(|(y, row)| {
+            let mut errors = 0;
+            let mut max_excess: f64 = 0.0;
+            for (x, &label) in row.iter().enumerate() {
+                if label == 0 || label > points.len() {
+                    errors += 1;
+                    continue;
+                }
+                let center = (
+                    (x as f64 + 0.5) * pixel_size.0,
+                    (y as f64 + 0.5) * pixel_size.1,
+                );
+                // Ties between seeds aren't errors
+                let excess =
+                    seeds.distance(points[label - 1], center) - seeds.nearest_distance(center);
+                if excess > 1e-12 * config.0.max(config.1) {
+                    errors += 1;
+                    max_excess = max_excess.max(excess);
+                }
+            }
+            (errors, max_excess)
+        })
+        .reduce(|| (0, 0.0), |a, b| (a.0 + b.0, a.1.max(b.1)));
+
+    Ok(AccuracyReport {
+        pixels: pixels.len(),
+        errors,
+        error_fraction: errors as f64 / pixels.len() as f64,
+        max_distance_excess,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{jfa_cpu, voronoi};
+
+    #[test]
+    fn test_accuracy() {
+        let points = vec![(1.0, 1.0), (3.5, 2.0), (8.0, 7.5), (2.0, 9.0), (6.0, 4.0)];
+        let config = (10.0, 10.0);
+        let reso = (128, 128);
+
+        // The exact diagram is the reference
+        let cells = voronoi::voronoi(&points, config).unwrap();
+        let exact = voronoi::rasterize(&cells, reso, config);
+        let report = accuracy(&exact, &points, config, reso, Metric::Euclidean).unwrap();
+        assert_eq!(report.errors, 0);
+        assert_eq!(report.max_distance_excess, 0.0);
+
+        // JFA only misses pixels close to the cell boundaries
+        let pixels = jfa_cpu::jfa(&points, None, config, reso, Metric::Euclidean, false).unwrap();
+        let report = accuracy(&pixels, &points, config, reso, Metric::Euclidean).unwrap();
+        assert!(report.error_fraction < 0.05);
+        assert!(report.max_distance_excess < 2.0 * config.0 / reso.0 as f64);
+
+        // Labeling everything with the first seed is mostly wrong
+        let report = accuracy(
+            &vec![1; reso.0 * reso.1],
+            &points,
+            config,
+            reso,
+            Metric::Euclidean,
+        );
+        let report = report.unwrap();
+        assert!(report.error_fraction > 0.5);
+    }
+}
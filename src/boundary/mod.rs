@@ -0,0 +1,90 @@
+// Boundary-conforming sampling: points are first spread along the boundary of the domain, then
+// Poisson disk sampling fills the interior from them, so that cells along the boundary aren't
+// ragged.
+
+use rand::Rng;
+
+use crate::domain::Polygon;
+use crate::mode3;
+
+/// Spreads points along closed `rings`, starting with their vertices, so that no two points are
+/// `d` or less apart.
+///
+/// Each edge is split evenly in as many segments as fit, and points too close to an already
+/// placed one, such as around sharp corners, are dropped.
+pub fn boundary_points(rings: &[Vec<(f64, f64)>], d: f64) -> Vec<(f64, f64)> {
+    let mut candidates: Vec<(f64, f64)> = rings.iter().flatten().copied().collect();
+    for ring in rings {
+        for (i, &a) in ring.iter().enumerate() {
+            let b = ring[(i + 1) % ring.len()];
+            let length = ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+            // Segments strictly longer than `d`
+            let segments = ((length / d).ceil() as usize).saturating_sub(1).max(1);
+            for j in 1..segments {
+                let t = j as f64 / segments as f64;
+                candidates.push((a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1)));
+            }
+        }
+    }
+
+    let mut points: Vec<(f64, f64)> = Vec::with_capacity(candidates.len());
+    for candidate in candidates {
+        let far_enough = points
+            .iter()
+            .all(|p| ((p.0 - candidate.0).powi(2) + (p.1 - candidate.1).powi(2)).sqrt() > d);
+        if far_enough {
+            points.push(candidate);
+        }
+    }
+    points
+}
+
+/// Poisson disk sampling of the `width * height` box, or of `domain` inside it, with points
+/// along the boundary first.
+pub fn generate_points<R: Rng>(
+    d: f64,
+    width: f64,
+    height: f64,
+    domain: Option<&Polygon>,
+    rng: &mut R,
+) -> Vec<(f64, f64)> {
+    let rings = match domain {
+        Some(domain) => std::iter::once(domain.exterior.clone())
+            .chain(domain.holes.iter().cloned())
+            .collect(),
+        None => vec![vec![
+            (0.0, 0.0),
+            (width, 0.0),
+            (width, height),
+            (0.0, height),
+        ]],
+    };
+    let seeds = boundary_points(&rings, d);
+    mode3::generate_points_from(d, width, height, &seeds, domain, rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_boundary_points() {
+        let d = 1.0;
+        let points = generate_points(d, 10.0, 5.0, None, &mut StdRng::seed_from_u64(42));
+
+        // Corners and evenly spread points along each side
+        for corner in [(0.0, 0.0), (10.0, 0.0), (10.0, 5.0), (0.0, 5.0)] {
+            assert!(points.contains(&corner));
+        }
+        let bottom = points.iter().filter(|p| p.1 == 0.0).count();
+        assert_eq!(bottom, 10);
+
+        for (i, &a) in points.iter().enumerate() {
+            for &b in &points[i + 1..] {
+                assert!(((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt() > d);
+            }
+        }
+    }
+}
@@ -0,0 +1,382 @@
+// Voronoi cell extraction from a JFA label grid.
+//
+// Vertices are detected on the pixel corners: a corner where three labels (or two labels on the
+// box boundary) meet is a Voronoi vertex. Neighboring corners are merged, and each cell is the
+// ring of the vertices it touches, which is well defined since Voronoi cells are convex.
+
+use std::collections::HashMap;
+
+use crate::periodic::nearest_image;
+
+/// Voronoi cells as polygons sharing their vertices.
+#[derive(Debug, Clone, Default)]
+pub struct VoronoiCells {
+    /// Vertex coordinates in domain units
+    pub vertices: Vec<(f64, f64)>,
+    /// For each seed, the indices of its polygon vertices in counterclockwise order.
+    /// Seeds whose region is too small to be resolved by the grid have an empty polygon.
+    pub cells: Vec<Vec<usize>>,
+    /// Size of the box when it wraps around. Vertices then lie in the box, and the polygons of
+    /// cells crossing an edge are unwrapped by `polygon`.
+    pub period: Option<(f64, f64)>,
+}
+
+impl VoronoiCells {
+    /// Returns the coordinates of the polygon of the seed `i`.
+    ///
+    /// In a periodic box, each vertex is taken as the image closest to the previous one, so that
+    /// the polygon is connected even if it crosses an edge of the box.
+    pub fn polygon(&self, i: usize) -> Vec<(f64, f64)> {
+        let mut polygon: Vec<(f64, f64)> = Vec::with_capacity(self.cells[i].len());
+        for &v in &self.cells[i] {
+            let vertex = match (self.period, polygon.last()) {
+                (Some(period), Some(&previous)) => {
+                    let (dx, dy) = nearest_image(
+                        (
+                            self.vertices[v].0 - previous.0,
+                            self.vertices[v].1 - previous.1,
+                        ),
+                        period,
+                    );
+                    (previous.0 + dx, previous.1 + dy)
+                }
+                _ => self.vertices[v],
+            };
+            polygon.push(vertex);
+        }
+        polygon
+    }
+
+    /// Returns the signed area of the polygon of the seed `i`, positive when counterclockwise.
+    pub fn area(&self, i: usize) -> f64 {
+        signed_area(&self.polygon(i))
+    }
+}
+
+/// Returns the signed area of a polygon, positive when counterclockwise.
+pub fn signed_area(polygon: &[(f64, f64)]) -> f64 {
+    let n = polygon.len();
+    (0..n)
+        .map(|i| {
+            let (x1, y1) = polygon[i];
+            let (x2, y2) = polygon[(i + 1) % n];
+            x1 * y2 - x2 * y1
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+/// Vertices shared between polygons, those closer than a tolerance being merged.
+#[derive(Debug, Clone)]
+pub(crate) struct VertexIndex {
+    pub vertices: Vec<(f64, f64)>,
+    index: HashMap<(i64, i64), usize>,
+    tolerance: f64,
+}
+
+impl VertexIndex {
+    pub fn new(tolerance: f64) -> Self {
+        VertexIndex {
+            vertices: Vec::new(),
+            index: HashMap::new(),
+            tolerance,
+        }
+    }
+
+    /// Returns the index of `vertex`, adding it unless a vertex close to it is already there.
+    pub fn insert(&mut self, vertex: (f64, f64)) -> usize {
+        let key = (
+            (vertex.0 / self.tolerance).round() as i64,
+            (vertex.1 / self.tolerance).round() as i64,
+        );
+        let found = (-1..=1)
+            .flat_map(|dx| (-1..=1).map(move |dy| (key.0 + dx, key.1 + dy)))
+            .find_map(|neighbor| self.index.get(&neighbor).copied());
+        found.unwrap_or_else(|| {
+            self.index.insert(key, self.vertices.len());
+            self.vertices.push(vertex);
+            self.vertices.len() - 1
+        })
+    }
+}
+
+/// Mean of positions, summed as offsets from the first one so that positions straddling a
+/// periodic edge average to a point between them.
+#[derive(Debug, Clone, Copy)]
+struct Mean {
+    first: (f64, f64),
+    offset: (f64, f64),
+    count: usize,
+}
+
+impl Mean {
+    fn new(first: (f64, f64)) -> Self {
+        Mean {
+            first,
+            offset: (0.0, 0.0),
+            count: 0,
+        }
+    }
+
+    /// Adds the position at `offset` from the first one.
+    fn add(&mut self, offset: (f64, f64)) {
+        self.offset = (self.offset.0 + offset.0, self.offset.1 + offset.1);
+        self.count += 1;
+    }
+
+    fn get(&self) -> (f64, f64) {
+        (
+            self.first.0 + self.offset.0 / self.count as f64,
+            self.first.1 + self.offset.1 / self.count as f64,
+        )
+    }
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Extracts the polygon of each of the `num_points` seeds from a label grid.
+///
+/// `pixels` holds `reso.0 * reso.1` labels, `i + 1` for the seed `i`, as returned by the JFA
+/// backends. Polygons are clipped to the `(0..config.0, 0..config.1)` box, unless it is
+/// `periodic`, in which case cells crossing an edge share their vertices with the opposite one.
+pub fn extract_cells(
+    pixels: &[usize],
+    reso: (usize, usize),
+    config: (f64, f64),
+    num_points: usize,
+    periodic: bool,
+) -> Result<VoronoiCells, &'static str> {
+    let (reso_x, reso_y) = reso;
+    if pixels.len() != reso_x * reso_y {
+        return Err("Label grid size doesn't match the resolution");
+    }
+    if pixels.iter().any(|&label| label == 0 || label > num_points) {
+        return Err("Label grid contains pixels without a valid seed");
+    }
+    let pixel_size = (config.0 / reso_x as f64, config.1 / reso_y as f64);
+    let period = periodic.then_some(config);
+    let offset = |from: (f64, f64), to: (f64, f64)| {
+        let d = (to.0 - from.0, to.1 - from.1);
+        period.map_or(d, |period| nearest_image(d, period))
+    };
+
+    // Look at every pixel corner and keep those where cells meet. In a periodic box, the
+    // corners on the right and top edges are the ones on the left and bottom edges.
+    let (nodes_x, nodes_y) = if periodic {
+        (reso_x, reso_y)
+    } else {
+        (reso_x + 1, reso_y + 1)
+    };
+    let mut node_labels: Vec<(usize, Vec<usize>)> = Vec::new();
+    let mut node_index = vec![usize::MAX; nodes_x * nodes_y];
+    for j in 0..nodes_y {
+        for i in 0..nodes_x {
+            let mut labels = Vec::with_capacity(4);
+            let (pi, pj) = if periodic {
+                ((i + reso_x - 1) % reso_x, (j + reso_y - 1) % reso_y)
+            } else {
+                (i.wrapping_sub(1), j.wrapping_sub(1))
+            };
+            for (px, py) in [(i, j), (pi, j), (i, pj), (pi, pj)] {
+                if px < reso_x && py < reso_y {
+                    let label = pixels[px + py * reso_x];
+                    if !labels.contains(&label) {
+                        labels.push(label);
+                    }
+                }
+            }
+
+            let on_border = !periodic && (i == 0 || i == reso_x || j == 0 || j == reso_y);
+            let is_corner = !periodic && (i == 0 || i == reso_x) && (j == 0 || j == reso_y);
+            if labels.len() >= 3 || (on_border && labels.len() >= 2) || is_corner {
+                node_index[i + j * nodes_x] = node_labels.len();
+                node_labels.push((i + j * nodes_x, labels));
+            }
+        }
+    }
+
+    // Merge touching nodes, which belong to the same vertex smeared over a few pixels
+    let mut parent: Vec<usize> = (0..node_labels.len()).collect();
+    for (n, &(node, _)) in node_labels.iter().enumerate() {
+        let (i, j) = (node % nodes_x, node / nodes_x);
+        for (di, dj) in [(1, 0), (0, 1), (1, 1), (-1, 1)] {
+            let (mut ni, mut nj) = (i as isize + di, j as isize + dj);
+            if periodic {
+                ni = ni.rem_euclid(nodes_x as isize);
+                nj = nj.rem_euclid(nodes_y as isize);
+            } else if ni < 0 || ni >= nodes_x as isize || nj >= nodes_y as isize {
+                continue;
+            }
+            let other = node_index[ni as usize + nj as usize * nodes_x];
+            if other != usize::MAX {
+                let (a, b) = (find(&mut parent, n), find(&mut parent, other));
+                parent[a] = b;
+            }
+        }
+    }
+
+    // Average the position of each group of nodes, relative to its first node so that groups
+    // straddling a periodic edge stay together, and collect the cells around it
+    let mut vertex_of_root = vec![usize::MAX; node_labels.len()];
+    let mut means: Vec<Mean> = Vec::new();
+    let mut cells: Vec<Vec<usize>> = vec![Vec::new(); num_points];
+    for (n, &(node, ref labels)) in node_labels.iter().enumerate() {
+        let position = (
+            (node % nodes_x) as f64 * pixel_size.0,
+            (node / nodes_x) as f64 * pixel_size.1,
+        );
+        let root = find(&mut parent, n);
+        if vertex_of_root[root] == usize::MAX {
+            vertex_of_root[root] = means.len();
+            means.push(Mean::new(position));
+        }
+        let vertex = vertex_of_root[root];
+
+        let mean = &mut means[vertex];
+        mean.add(offset(mean.first, position));
+
+        for &label in labels {
+            if !cells[label - 1].contains(&vertex) {
+                cells[label - 1].push(vertex);
+            }
+        }
+    }
+    let vertices: Vec<(f64, f64)> = means
+        .iter()
+        .map(|mean| {
+            let (x, y) = mean.get();
+            if periodic {
+                return (x.rem_euclid(config.0), y.rem_euclid(config.1));
+            }
+            // Vertices gathered on the box boundary stay on it
+            let snap = |v: f64, max: f64, size: f64| {
+                if v < size {
+                    0.0
+                } else if v > max - size {
+                    max
+                } else {
+                    v
+                }
+            };
+            (
+                snap(x, config.0, pixel_size.0 / 2.0),
+                snap(y, config.1, pixel_size.1 / 2.0),
+            )
+        })
+        .collect();
+
+    // Order each ring counterclockwise around the center of its pixels
+    let mut centers: Vec<Option<Mean>> = vec![None; num_points];
+    for (p, &label) in pixels.iter().enumerate() {
+        let position = (
+            ((p % reso_x) as f64 + 0.5) * pixel_size.0,
+            ((p / reso_x) as f64 + 0.5) * pixel_size.1,
+        );
+        let center = centers[label - 1].get_or_insert(Mean::new(position));
+        center.add(offset(center.first, position));
+    }
+    for (cell, center) in cells.iter_mut().zip(centers) {
+        let Some(center) = center.filter(|_| cell.len() >= 3) else {
+            cell.clear();
+            continue;
+        };
+        let center = center.get();
+        let angle = |v: usize| {
+            let (dx, dy) = offset(center, vertices[v]);
+            dy.atan2(dx)
+        };
+        cell.sort_by(|&a, &b| angle(a).total_cmp(&angle(b)));
+    }
+
+    Ok(VoronoiCells {
+        vertices,
+        cells,
+        period,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jfa_cpu::jfa;
+    use crate::metric::Metric;
+
+    fn assert_close(a: (f64, f64), b: (f64, f64), tolerance: f64) {
+        assert!(
+            (a.0 - b.0).abs() <= tolerance && (a.1 - b.1).abs() <= tolerance,
+            "{:?} isn't close to {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn test_two_cells() {
+        let points = vec![(2.0, 5.0), (8.0, 5.0)];
+        let config = (10.0, 10.0);
+        let reso = (100, 100);
+        let pixels = jfa(&points, None, config, reso, Metric::Euclidean, false).unwrap();
+
+        let cells = extract_cells(&pixels, reso, config, points.len(), false).unwrap();
+
+        let left = cells.polygon(0);
+        assert_eq!(left.len(), 4);
+        for (vertex, expected) in
+            left.iter()
+                .zip([(0.0, 0.0), (5.0, 0.0), (5.0, 10.0), (0.0, 10.0)])
+        {
+            assert_close(*vertex, expected, 0.2);
+        }
+        assert!((cells.area(0) - 50.0).abs() < 2.0);
+        assert!((cells.area(1) - 50.0).abs() < 2.0);
+    }
+
+    #[test]
+    fn test_shared_center_vertex() {
+        let points = vec![(2.5, 2.5), (7.5, 2.5), (2.5, 7.5), (7.5, 7.5)];
+        let config = (10.0, 10.0);
+        let reso = (128, 128);
+        let pixels = jfa(&points, None, config, reso, Metric::Euclidean, false).unwrap();
+
+        let cells = extract_cells(&pixels, reso, config, points.len(), false).unwrap();
+
+        // The four cells share one vertex in the middle of the box
+        let shared: Vec<&usize> = cells.cells[0]
+            .iter()
+            .filter(|v| cells.cells[1..].iter().all(|cell| cell.contains(v)))
+            .collect();
+        assert_eq!(shared.len(), 1);
+        assert_close(cells.vertices[*shared[0]], (5.0, 5.0), 0.2);
+
+        let total: f64 = (0..points.len()).map(|i| cells.area(i)).sum();
+        assert!((total - 100.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_periodic_cells() {
+        // Seeds on a staggered lattice, the cells of the left column cross the left edge
+        let points = vec![(1.0, 2.5), (1.0, 7.5), (6.0, 0.0), (6.0, 5.0)];
+        let config = (10.0, 10.0);
+        let reso = (128, 128);
+        let pixels = jfa(&points, None, config, reso, Metric::Euclidean, true).unwrap();
+
+        let cells = extract_cells(&pixels, reso, config, points.len(), true).unwrap();
+
+        // The cells tile the torus, and each polygon is in one piece
+        let total: f64 = (0..points.len()).map(|i| cells.area(i)).sum();
+        assert!((total - 100.0).abs() < 1.0);
+        for i in 0..points.len() {
+            assert!((cells.area(i) - 25.0).abs() < 1.0);
+        }
+        assert!(cells
+            .vertices
+            .iter()
+            .all(|v| (0.0..10.0).contains(&v.0) && (0.0..10.0).contains(&v.1)));
+    }
+}
@@ -1,6 +1,11 @@
 use clap::{Parser, ValueEnum};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use std::path::PathBuf;
 
+use crate::domain::Polygon;
+use crate::metric::Metric;
+
 /// Point generation on a rectangle.
 #[derive(Parser, Debug)]
 #[command(version, about = "Point generation on a rectangle.")]
@@ -17,6 +22,35 @@ pub struct Cli {
     #[arg(short = 'd', long = "distance", default_value_t = 1.0)]
     pub d: f64,
 
+    /// Sets the maximal distance of points for `variable-poisson-disk` [default: 4 * d]
+    #[arg(long = "max-distance")]
+    pub max_distance: Option<f64>,
+
+    /// Sets the minimal distance of points for `variable-poisson-disk` as an expression of `x`
+    /// and `y`, clamped between `d` and `max-distance`
+    #[arg(long = "sizing", value_name = "EXPR", conflicts_with = "sizing_image")]
+    pub sizing: Option<String>,
+
+    /// Sets the minimal distance of points for `variable-poisson-disk` from a grayscale image
+    /// stretched over the box, black being `d` and white `max-distance`
+    #[arg(long = "sizing-image", value_name = "FILE")]
+    pub sizing_image: Option<PathBuf>,
+
+    /// Makes the box periodic, opposite edges wrapping around for sampling and cells
+    #[arg(long = "periodic")]
+    pub periodic: bool,
+
+    /// Restricts points and cells to a polygon with holes inside the box, read from a file with
+    /// one `x,y` vertex per line and rings separated by empty lines, the first one being the
+    /// exterior
+    #[arg(long = "domain", value_name = "FILE")]
+    pub domain: Option<PathBuf>,
+
+    /// Places points along the boundary of the box or domain first, at the minimal distance,
+    /// then fills the interior with Poisson disk sampling
+    #[arg(long = "boundary")]
+    pub boundary: bool,
+
     /// Sets the width of the box
     #[arg(short = 'x', default_value_t = 10.0)]
     pub x: f64,
@@ -25,21 +59,171 @@ pub struct Cli {
     #[arg(short = 'y', default_value_t = 10.0)]
     pub y: f64,
 
+    /// Sets the depth of the box, sampling it in 3D
+    #[arg(short = 'z')]
+    pub z: Option<f64>,
+
     /// Exports point list to a CSV-formatted file
     #[arg(short = 'e', long = "export", value_name = "FILE")]
     pub export: Option<PathBuf>,
 
-    /// Plot options: `points`, `jfa`, or `none`
+    /// Imports points, and optionally their weights, from a CSV, XYZ or PLY file instead of
+    /// generating them, which implies the import mode
+    #[arg(short = 'i', long = "import", value_name = "FILE")]
+    pub import: Option<PathBuf>,
+
+    /// Exports the Delaunay triangulation of the points to an OFF file
+    #[arg(short = 't', long = "triangulation", value_name = "FILE")]
+    pub triangulation: Option<PathBuf>,
+
+    /// Exports a mesh for ParaView, as an XML VTU file when FILE ends with `.vtu` and as a legacy
+    /// VTK file otherwise
+    #[arg(long = "vtk", value_name = "FILE")]
+    pub vtk: Option<PathBuf>,
+
+    /// Exports a mesh for finite element codes, as a Medit file when FILE ends with `.mesh` and
+    /// as a Gmsh file otherwise
+    #[arg(long = "mesh", value_name = "FILE")]
+    pub mesh: Option<PathBuf>,
+
+    /// Sets the mesh exported with --vtk and --mesh
+    #[arg(long = "mesh-type", default_value = "voronoi")]
+    pub mesh_type: MeshType,
+
+    /// Saves the plot to an HTML, PNG or SVG file instead of opening it in a browser
+    #[arg(long = "plot-output", value_name = "FILE")]
+    pub plot_output: Option<PathBuf>,
+
+    /// Exports the JFA label of each pixel, 0 outside the domain and `i + 1` for the seed `i`
+    #[arg(long = "labels", value_name = "FILE")]
+    pub labels: Option<PathBuf>,
+
+    /// Sets the format of the labels exported with --labels, only npy being available in 3D
+    #[arg(long = "labels-format", default_value = "colored")]
+    pub labels_format: LabelFormat,
+
+    /// Exports the distance from each JFA pixel to its nearest seed to a CSV-formatted file
+    #[arg(long = "distance-field", value_name = "FILE")]
+    pub distance_field: Option<PathBuf>,
+
+    /// Plot options: `points`, `jfa`, `distance`, or `none`
     #[arg(short = 'p', long = "plot", default_value = "jfa", value_enum)]
     pub plot: PlotMode,
 
-    /// Sets the JFA mode: `cpu`, `gpu`, or `none`
+    /// Sets the JFA mode: `cpu`, `cpu-parallel`, `gpu`, `exact`, or `none`
     #[arg(short = 'j', long = "jfa-mode", default_value = "gpu", value_enum)]
     pub jfa_mode: JfaMode,
 
-    /// Sets the resolution for JFA
+    /// Sets the metric used to find the nearest seed: `euclidean`, `manhattan`, `chebyshev`,
+    /// `lp`, or `anisotropic`
+    #[arg(long = "metric", default_value = "euclidean", value_enum)]
+    pub metric: MetricMode,
+
+    /// Sets the exponent of the `lp` metric
+    #[arg(long = "lp", default_value_t = 2.0)]
+    pub lp: f64,
+
+    /// Sets the `m11,m12,m22` coefficients of the `anisotropic` metric tensor
+    #[arg(long = "tensor", value_delimiter = ',', default_values_t = [1.0, 0.0, 1.0])]
+    pub tensor: Vec<f64>,
+
+    /// Sets the resolution for JFA along the longest side of the box
     #[arg(short = 'r', long = "res", default_value_t = 512)]
     pub res: u32,
+
+    /// Sets the horizontal JFA resolution, overriding the one derived from the box aspect ratio
+    #[arg(long = "res-x")]
+    pub res_x: Option<u32>,
+
+    /// Sets the vertical JFA resolution, overriding the one derived from the box aspect ratio
+    #[arg(long = "res-y")]
+    pub res_y: Option<u32>,
+
+    /// Seeds the random number generator, making runs reproducible
+    #[arg(short = 's', long = "seed")]
+    pub seed: Option<u64>,
+
+    /// Compares the JFA cells to the nearest seed of each pixel and reports the errors
+    #[arg(short = 'a', long = "accuracy")]
+    pub accuracy: bool,
+
+    /// Relaxes the points with up to N Lloyd iterations on the JFA cells, towards a centroidal
+    /// Voronoi tessellation
+    #[arg(long = "lloyd", value_name = "N", default_value_t = 0)]
+    pub lloyd: usize,
+
+    /// Stops the Lloyd iterations once no point moves by more than this distance
+    #[arg(long = "lloyd-tolerance", default_value_t = 1e-4)]
+    pub lloyd_tolerance: f64,
+}
+
+impl Cli {
+    /// Returns the JFA grid size in pixels as `(width, height)`.
+    ///
+    /// Sides that aren't set explicitly follow the aspect ratio of the box, so that pixels stay
+    /// square: `res` pixels along the longest side when neither is given, or the explicit side
+    /// scaled when only one is.
+    pub fn resolution(&self) -> (usize, usize) {
+        let scaled = |res: u32, ratio: f64| ((res as f64 * ratio).round() as usize).max(1);
+        match (self.res_x, self.res_y) {
+            (Some(res_x), Some(res_y)) => (res_x as usize, res_y as usize),
+            (Some(res_x), None) => (res_x as usize, scaled(res_x, self.y / self.x)),
+            (None, Some(res_y)) => (scaled(res_y, self.x / self.y), res_y as usize),
+            (None, None) if self.x >= self.y => {
+                (self.res as usize, scaled(self.res, self.y / self.x))
+            }
+            (None, None) => (scaled(self.res, self.x / self.y), self.res as usize),
+        }
+    }
+
+    /// Returns the voxel counts of a 3D JFA, `--res` voxels along the longest side of the box
+    /// and proportionally fewer along the others.
+    pub fn resolution_3d(&self) -> (usize, usize, usize) {
+        let z = self.z.unwrap_or(self.x);
+        let longest = self.x.max(self.y).max(z);
+        let scaled = |side: f64| ((self.res as f64 * side / longest).round() as usize).max(1);
+        (scaled(self.x), scaled(self.y), scaled(z))
+    }
+
+    /// Returns the metric selected with `--metric`, checking its parameters.
+    pub fn metric(&self) -> Result<Metric, &'static str> {
+        match self.metric {
+            MetricMode::Euclidean => Ok(Metric::Euclidean),
+            MetricMode::Manhattan => Ok(Metric::Manhattan),
+            MetricMode::Chebyshev => Ok(Metric::Chebyshev),
+            MetricMode::Lp => Metric::lp(self.lp),
+            MetricMode::Anisotropic => match self.tensor[..] {
+                [m11, m12, m22] => Metric::anisotropic([m11, m12, m22]),
+                _ => Err("The metric tensor needs 3 coefficients"),
+            },
+        }
+    }
+
+    /// Returns the `(d, max-distance)` range of the distance between points.
+    pub fn distance_range(&self) -> (f64, f64) {
+        (self.d, self.max_distance.unwrap_or(4.0 * self.d))
+    }
+
+    /// Returns the polygon read from `--domain`, checking that it lies inside the box.
+    pub fn domain(&self) -> Result<Option<Polygon>, &'static str> {
+        let Some(ref domain_path) = self.domain else {
+            return Ok(None);
+        };
+        let domain = Polygon::read(domain_path)?;
+        let (min, max) = domain.bounding_box();
+        if min.0 < 0.0 || min.1 < 0.0 || max.0 > self.x || max.1 > self.y {
+            return Err("The domain must lie inside the box");
+        }
+        Ok(Some(domain))
+    }
+
+    /// Returns a random number generator seeded with `seed`, or from entropy when it isn't set.
+    pub fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
 }
 
 /// Point generation modes
@@ -48,6 +232,10 @@ pub enum Mode {
     GridWithN,
     GridWithD,
     PoissonDisk,
+    ParallelPoissonDisk,
+    VariablePoissonDisk,
+    /// Reads the points from the `--import` file
+    Import,
 }
 
 /// Plotting options
@@ -55,34 +243,151 @@ pub enum Mode {
 pub enum PlotMode {
     Points,
     Jfa,
+    Distance,
     None,
 }
 
+/// Meshes exported for ParaView and finite element codes
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, ValueEnum)]
+pub enum MeshType {
+    /// The points, with their seed index
+    Points,
+    /// The Voronoi cells, with their area, neighbor count and seed index
+    Voronoi,
+    /// The Delaunay triangles, with their area and neighbor count
+    Delaunay,
+}
+
+/// Label grid files
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, ValueEnum)]
+pub enum LabelFormat {
+    /// PNG with a color per seed, indexed by label up to 255 seeds
+    Colored,
+    /// 16-bit grayscale PNG
+    Gray16,
+    /// 32-bit grayscale TIFF, to a `.tif` or `.tiff` path
+    Gray32,
+    /// NumPy array of `u32`
+    Npy,
+}
+
+/// Distance metrics
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, ValueEnum)]
+pub enum MetricMode {
+    Euclidean,
+    Manhattan,
+    Chebyshev,
+    Lp,
+    Anisotropic,
+}
+
 /// JFA modes
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, ValueEnum)]
 pub enum JfaMode {
     Cpu,
+    CpuParallel,
     Gpu,
+    Exact,
     None,
 }
 
+impl JfaMode {
+    /// Describes how the cells are computed, for the progress messages.
+    pub fn describe(self) -> String {
+        match self {
+            JfaMode::Cpu => "cells using CPU".to_string(),
+            JfaMode::CpuParallel => {
+                format!("cells using {} CPU threads", rayon::current_num_threads())
+            }
+            JfaMode::Gpu => "cells using GPU".to_string(),
+            JfaMode::Exact => "exact cells".to_string(),
+            JfaMode::None => "no cells".to_string(),
+        }
+    }
+}
+
 pub fn print_config(cli: &Cli) {
     println!("Display help with option -h or --help.");
     println!("The program will run with the following configuration:");
     println!("Mode: {:?}", cli.mode);
     println!("Number of points (n): {}", cli.n);
     println!("Minimal distance (d): {}", cli.d);
-    println!(
-        "Box dimensions: width (x) = {}, height (y) = {}",
-        cli.x, cli.y
-    );
+    if cli.mode == Mode::VariablePoissonDisk {
+        println!("Maximal distance: {}", cli.distance_range().1);
+        if let Some(ref sizing) = cli.sizing {
+            println!("Sizing expression: {}", sizing);
+        }
+        if let Some(ref sizing_path) = cli.sizing_image {
+            println!("Sizing image: {}", sizing_path.display());
+        }
+    }
+    match cli.z {
+        Some(z) => println!(
+            "Box dimensions: width (x) = {}, height (y) = {}, depth (z) = {}",
+            cli.x, cli.y, z
+        ),
+        None => println!(
+            "Box dimensions: width (x) = {}, height (y) = {}",
+            cli.x, cli.y
+        ),
+    }
+    if cli.periodic {
+        println!("Periodic box: {}", cli.periodic);
+    }
+    if let Some(ref domain_path) = cli.domain {
+        println!("Domain: {}", domain_path.display());
+    }
+    if cli.boundary {
+        println!("Boundary points: {}", cli.boundary);
+    }
     if let Some(ref export_path) = cli.export {
         println!("Export path: {}", export_path.display());
     }
+    if let Some(ref import_path) = cli.import {
+        println!("Import path: {}", import_path.display());
+    }
+    if let Some(ref triangulation_path) = cli.triangulation {
+        println!("Triangulation path: {}", triangulation_path.display());
+    }
+    if let Some(ref labels_path) = cli.labels {
+        println!(
+            "Labels path: {} ({:?})",
+            labels_path.display(),
+            cli.labels_format
+        );
+    }
+    if let Some(ref plot_path) = cli.plot_output {
+        println!("Plot path: {}", plot_path.display());
+    }
+    if let Some(ref vtk_path) = cli.vtk {
+        println!("VTK path: {} ({:?})", vtk_path.display(), cli.mesh_type);
+    }
+    if let Some(ref mesh_path) = cli.mesh {
+        println!("Mesh path: {} ({:?})", mesh_path.display(), cli.mesh_type);
+    }
+    if let Some(ref distance_path) = cli.distance_field {
+        println!("Distance path: {}", distance_path.display());
+    }
+    if let Some(seed) = cli.seed {
+        println!("Seed: {}", seed);
+    }
     println!("Plot mode: {:?}", cli.plot);
     println!("JFA mode: {:?}", cli.jfa_mode);
     if cli.jfa_mode != JfaMode::None {
-        println!("JFA resolution: {}", cli.res);
+        let (res_x, res_y) = cli.resolution();
+        println!("JFA resolution: {}*{}", res_x, res_y);
+        match cli.metric {
+            MetricMode::Lp => println!("Metric: {:?} (p = {})", cli.metric, cli.lp),
+            MetricMode::Anisotropic => println!("Metric: {:?} {:?}", cli.metric, cli.tensor),
+            _ => println!("Metric: {:?}", cli.metric),
+        }
+        println!("Accuracy report: {}", cli.accuracy);
+        if cli.lloyd > 0 {
+            println!(
+                "Lloyd iterations: {} (tolerance {})",
+                cli.lloyd, cli.lloyd_tolerance
+            );
+        }
     }
     println!();
 }
@@ -90,3 +395,38 @@ pub fn print_config(cli: &Cli) {
 pub fn parse() -> Cli {
     Cli::parse()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolution_follows_aspect_ratio() {
+        let cli = Cli::parse_from(["blue_noise", "-x", "100", "-y", "10", "-r", "500"]);
+        assert_eq!(cli.resolution(), (500, 50));
+
+        let cli = Cli::parse_from(["blue_noise", "-x", "10", "-y", "20", "--res-x", "64"]);
+        assert_eq!(cli.resolution(), (64, 128));
+
+        let cli = Cli::parse_from(["blue_noise", "--res-x", "30", "--res-y", "40"]);
+        assert_eq!(cli.resolution(), (30, 40));
+    }
+
+    #[test]
+    fn test_metric() {
+        let cli = Cli::parse_from(["blue_noise"]);
+        assert_eq!(cli.metric(), Ok(Metric::Euclidean));
+
+        let cli = Cli::parse_from(["blue_noise", "--metric", "lp", "--lp", "3"]);
+        assert_eq!(cli.metric(), Ok(Metric::Lp(3.0)));
+
+        let cli = Cli::parse_from([
+            "blue_noise",
+            "--metric",
+            "anisotropic",
+            "--tensor",
+            "2,0.5,1",
+        ]);
+        assert_eq!(cli.metric(), Ok(Metric::Anisotropic([2.0, 0.5, 1.0])));
+    }
+}
@@ -0,0 +1,114 @@
+// Conversion of the Voronoi cells and triangulations into honeycomb combinatorial maps.
+
+use std::collections::HashMap;
+
+use honeycomb::prelude::{CMap2, CMapBuilder, Vertex2};
+
+use crate::cells::VoronoiCells;
+use crate::delaunay::Triangulation;
+
+/// Builds a `CMap2` with one face per Voronoi cell.
+///
+/// Darts of each face are 1-linked following the counterclockwise order of the polygon, and edges
+/// shared by two neighbor cells are 2-linked. Edges on the box boundary stay 2-free, while in a
+/// periodic box every edge is shared and the map is closed. Faces are created in seed order,
+/// cells too small to be extracted are skipped.
+pub fn build_cmap2(cells: &VoronoiCells) -> Result<CMap2<f64>, &'static str> {
+    faces_to_cmap2(&cells.vertices, &cells.cells)
+}
+
+/// Builds a `CMap2` with one face per triangle, in the order of `triangulation.triangles`.
+///
+/// Edges on the box boundary stay 2-free.
+pub fn triangulation_to_cmap2(triangulation: &Triangulation) -> Result<CMap2<f64>, &'static str> {
+    faces_to_cmap2(&triangulation.vertices, &triangulation.triangles)
+}
+
+fn faces_to_cmap2<F: AsRef<[usize]>>(
+    vertices: &[(f64, f64)],
+    faces: &[F],
+) -> Result<CMap2<f64>, &'static str> {
+    let n_darts: usize = faces.iter().map(|face| face.as_ref().len()).sum();
+    if n_darts == 0 {
+        return Err("No cell to build the map from");
+    }
+
+    let mut map: CMap2<f64> = CMapBuilder::default()
+        .n_darts(n_darts)
+        .build()
+        .map_err(|_| "Unable to build the map")?;
+
+    // Dart 0 is the null dart, so the darts of the map are 1..=n_darts
+    let mut dart = 1;
+    let mut edges: HashMap<(usize, usize), u32> = HashMap::new();
+    let mut dart_vertex: Vec<(u32, usize)> = Vec::with_capacity(n_darts);
+    for cell in faces
+        .iter()
+        .map(AsRef::as_ref)
+        .filter(|cell| !cell.is_empty())
+    {
+        let first = dart;
+        for (i, &vertex) in cell.iter().enumerate() {
+            let next_vertex = cell[(i + 1) % cell.len()];
+            let next_dart = if i + 1 == cell.len() { first } else { dart + 1 };
+            map.one_link(dart, next_dart);
+
+            // The neighbor cell walks the same edge in the opposite direction
+            if let Some(&opposite) = edges.get(&(next_vertex, vertex)) {
+                map.two_link(dart, opposite);
+            } else {
+                edges.insert((vertex, next_vertex), dart);
+            }
+
+            dart_vertex.push((dart, vertex));
+            dart += 1;
+        }
+    }
+
+    // Vertex identifiers are only known once the topology is complete
+    let mut inserted = vec![false; n_darts + 1];
+    for (dart, vertex) in dart_vertex {
+        let vertex_id = map.vertex_id(dart);
+        if !inserted[vertex_id as usize] {
+            map.insert_vertex(vertex_id, Vertex2::from(vertices[vertex]));
+            inserted[vertex_id as usize] = true;
+        }
+    }
+
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_cells() {
+        // Two unit squares side by side, sharing the edge (1, 0) - (1, 1)
+        let cells = VoronoiCells {
+            vertices: vec![
+                (0.0, 0.0),
+                (1.0, 0.0),
+                (2.0, 0.0),
+                (0.0, 1.0),
+                (1.0, 1.0),
+                (2.0, 1.0),
+            ],
+            cells: vec![vec![0, 1, 4, 3], vec![1, 2, 5, 4]],
+            period: None,
+        };
+
+        let map = build_cmap2(&cells).unwrap();
+
+        // Darts 2 and 8 walk the shared edge
+        assert_eq!(map.beta::<2>(2), 8);
+        assert_eq!(map.beta::<2>(8), 2);
+        let linked = (1..=8).filter(|&dart| map.beta::<2>(dart) != 0).count();
+        assert_eq!(linked, 2);
+
+        // Both faces see the same vertex at the end of the shared edge
+        assert_eq!(map.vertex_id(2), map.vertex_id(5));
+        let vertex = map.vertex(map.vertex_id(5)).unwrap();
+        assert_eq!((vertex.x(), vertex.y()), (1.0, 0.0));
+    }
+}
@@ -0,0 +1,168 @@
+// Delaunay triangulation of the generated points, covering the whole box.
+//
+// The box corners are added to the points so that the convex hull is the box itself, and the
+// box sides are inserted as constraints, split at every point lying on them.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use spade::{ConstrainedDelaunayTriangulation, Point2, Triangulation as _};
+
+/// Sides of the box, in counterclockwise order starting from the bottom one.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Side {
+    Bottom,
+    Right,
+    Top,
+    Left,
+}
+
+/// Triangle mesh of the box.
+#[derive(Debug, Clone, Default)]
+pub struct Triangulation {
+    /// The input points, in order, followed by the box corners that weren't already among them
+    pub vertices: Vec<(f64, f64)>,
+    /// Vertex indices of each triangle, in counterclockwise order
+    pub triangles: Vec<[usize; 3]>,
+    /// Constrained edges along the box, oriented counterclockwise, with the side they lie on
+    pub boundary_edges: Vec<(usize, usize, Side)>,
+}
+
+impl Triangulation {
+    /// Writes the triangulation as an OFF file, with a zero `z` coordinate.
+    pub fn write_off(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "OFF")?;
+        writeln!(file, "{} {} 0", self.vertices.len(), self.triangles.len())?;
+        for (x, y) in &self.vertices {
+            writeln!(file, "{} {} 0", x, y)?;
+        }
+        for [a, b, c] in &self.triangles {
+            writeln!(file, "3 {} {} {}", a, b, c)?;
+        }
+        file.flush()
+    }
+}
+
+/// Triangulates `points` and the corners of the `(0..config.0, 0..config.1)` box.
+pub fn triangulate(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+) -> Result<Triangulation, &'static str> {
+    let tolerance = 1e-12 * config.0.max(config.1);
+    let inside = |&(x, y): &(f64, f64)| {
+        (-tolerance..=config.0 + tolerance).contains(&x)
+            && (-tolerance..=config.1 + tolerance).contains(&y)
+    };
+    if !points.iter().all(inside) {
+        return Err("Points must lie inside the box to be triangulated");
+    }
+
+    let mut vertices = points.to_vec();
+    for corner in [(0.0, 0.0), (config.0, 0.0), config, (0.0, config.1)] {
+        if !vertices.contains(&corner) {
+            vertices.push(corner);
+        }
+    }
+
+    // Duplicated points share one vertex of the triangulation, `index_of` maps it back to the
+    // first of them
+    let mut cdt: ConstrainedDelaunayTriangulation<Point2<f64>> =
+        ConstrainedDelaunayTriangulation::new();
+    let mut handles = Vec::with_capacity(vertices.len());
+    let mut index_of = Vec::with_capacity(vertices.len());
+    for (i, &(x, y)) in vertices.iter().enumerate() {
+        let handle = cdt
+            .insert(Point2::new(x, y))
+            .map_err(|_| "Unable to insert a point in the triangulation")?;
+        if handle.index() == index_of.len() {
+            index_of.push(i);
+        }
+        handles.push(handle);
+    }
+
+    // Walk each side counterclockwise through the vertices lying on it
+    let mut boundary_edges = Vec::new();
+    let on_side = |side: Side, (x, y): (f64, f64)| match side {
+        Side::Bottom => y.abs() <= tolerance,
+        Side::Right => (x - config.0).abs() <= tolerance,
+        Side::Top => (y - config.1).abs() <= tolerance,
+        Side::Left => x.abs() <= tolerance,
+    };
+    for side in [Side::Bottom, Side::Right, Side::Top, Side::Left] {
+        let mut on_this_side: Vec<usize> = (0..index_of.len())
+            .filter(|&v| on_side(side, vertices[index_of[v]]))
+            .collect();
+        on_this_side.sort_by(|&a, &b| {
+            let (pa, pb) = (vertices[index_of[a]], vertices[index_of[b]]);
+            match side {
+                Side::Bottom => pa.0.total_cmp(&pb.0),
+                Side::Right => pa.1.total_cmp(&pb.1),
+                Side::Top => pb.0.total_cmp(&pa.0),
+                Side::Left => pb.1.total_cmp(&pa.1),
+            }
+        });
+        for pair in on_this_side.windows(2) {
+            cdt.add_constraint(handles[index_of[pair[0]]], handles[index_of[pair[1]]]);
+            boundary_edges.push((index_of[pair[0]], index_of[pair[1]], side));
+        }
+    }
+
+    let triangles = cdt
+        .inner_faces()
+        .map(|face| {
+            let [a, b, c] = face.vertices();
+            [
+                index_of[a.fix().index()],
+                index_of[b.fix().index()],
+                index_of[c.fix().index()],
+            ]
+        })
+        .collect();
+
+    Ok(Triangulation {
+        vertices,
+        triangles,
+        boundary_edges,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triangulate_box() {
+        let points = vec![(5.0, 5.0), (5.0, 0.0)];
+        let config = (10.0, 10.0);
+
+        let triangulation = triangulate(&points, config).unwrap();
+
+        // The middle point is linked to the 5 boundary vertices
+        assert_eq!(triangulation.vertices.len(), 6);
+        assert_eq!(triangulation.triangles.len(), 5);
+        assert!(triangulation.triangles.iter().all(|t| t.contains(&0)));
+
+        // The bottom side is split at (5, 0)
+        assert_eq!(triangulation.boundary_edges.len(), 5);
+        assert_eq!(triangulation.boundary_edges[0], (2, 1, Side::Bottom));
+        assert_eq!(triangulation.boundary_edges[1], (1, 3, Side::Bottom));
+
+        // Triangles are counterclockwise and cover the box
+        let area: f64 = triangulation
+            .triangles
+            .iter()
+            .map(|&[a, b, c]| {
+                let (pa, pb, pc) = (
+                    triangulation.vertices[a],
+                    triangulation.vertices[b],
+                    triangulation.vertices[c],
+                );
+                ((pb.0 - pa.0) * (pc.1 - pa.1) - (pc.0 - pa.0) * (pb.1 - pa.1)) / 2.0
+            })
+            .inspect(|&area| assert!(area > 0.0))
+            .sum();
+        assert!((area - 100.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,131 @@
+// Distance transform derived from a label grid.
+//
+// Once JFA has labeled each pixel with its nearest seed, the distance to that seed is the
+// Euclidean distance transform of the seeds, whichever backend produced the labels.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use rayon::prelude::*;
+
+use crate::metric::Metric;
+use crate::periodic::nearest_image;
+
+/// Distance from each pixel center to its nearest seed, in the same layout as the label grid.
+#[derive(Debug, Clone, Default)]
+pub struct DistanceField {
+    pub reso: (usize, usize),
+    /// Size of a pixel in domain units
+    pub pixel_size: (f64, f64),
+    /// Distances in domain units, for the metric used to generate the labels
+    pub distances: Vec<f64>,
+    /// Vectors from each pixel center to its nearest seed, when requested
+    pub vectors: Option<Vec<(f64, f64)>>,
+}
+
+impl DistanceField {
+    /// Writes one `x,y,distance` line per pixel, `x,y` being the pixel center, followed by the
+    /// `dx,dy` vector to the nearest seed when available.
+    pub fn write_csv(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        for (p, distance) in self.distances.iter().enumerate() {
+            let x = ((p % self.reso.0) as f64 + 0.5) * self.pixel_size.0;
+            let y = ((p / self.reso.0) as f64 + 0.5) * self.pixel_size.1;
+            match self.vectors {
+                Some(ref vectors) => {
+                    let (dx, dy) = vectors[p];
+                    writeln!(file, "{},{},{},{},{}", x, y, distance, dx, dy)?
+                }
+                None => writeln!(file, "{},{},{}", x, y, distance)?,
+            }
+        }
+        file.flush()
+    }
+}
+
+/// Computes the distance from each pixel center to the seed it is labeled with, or to its nearest
+/// image across the box edges when `periodic`. Pixels labeled 0, outside the domain, are at an
+/// undefined (NaN) distance.
+pub fn distance_transform(
+    pixels: &[usize],
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    reso: (usize, usize),
+    metric: Metric,
+    with_vectors: bool,
+    periodic: bool,
+) -> Result<DistanceField, &'static str> {
+    let (reso_x, reso_y) = reso;
+    if pixels.len() != reso_x * reso_y {
+        return Err("Label grid size doesn't match the resolution");
+    }
+    if pixels.iter().any(|&label| label > points.len()) {
+        return Err("Label grid contains pixels without a valid seed");
+    }
+    let pixel_size = (config.0 / reso_x as f64, config.1 / reso_y as f64);
+
+    let vectors: Vec<(f64, f64)> = pixels
+        .par_iter()
+        .enumerate()
+        .map(|(p, &label)| {
+            if label == 0 {
+                return (f64::NAN, f64::NAN);
+            }
+            let seed = points[label - 1];
+            let x = ((p % reso_x) as f64 + 0.5) * pixel_size.0;
+            let y = ((p / reso_x) as f64 + 0.5) * pixel_size.1;
+            let (dx, dy) = (seed.0 - x, seed.1 - y);
+            if periodic {
+                nearest_image((dx, dy), config)
+            } else {
+                (dx, dy)
+            }
+        })
+        .collect();
+    let distances = vectors
+        .par_iter()
+        .map(|&(dx, dy)| metric.distance(dx, dy))
+        .collect();
+
+    Ok(DistanceField {
+        reso,
+        pixel_size,
+        distances,
+        vectors: with_vectors.then_some(vectors),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jfa_cpu::jfa;
+
+    #[test]
+    fn test_distance_transform() {
+        let points = vec![(2.5, 5.0), (7.5, 5.0)];
+        let config = (10.0, 10.0);
+        let reso = (20, 20);
+        let pixels = jfa(&points, None, config, reso, Metric::Euclidean, false).unwrap();
+
+        let field = distance_transform(
+            &pixels,
+            &points,
+            config,
+            reso,
+            Metric::Euclidean,
+            true,
+            false,
+        )
+        .unwrap();
+
+        // The pixel centered on (0.25, 5.25) is 2.25 left of the first seed
+        let p = 10 * reso.0;
+        assert!((field.distances[p] - (2.25_f64.powi(2) + 0.25_f64.powi(2)).sqrt()).abs() < 1e-12);
+        assert_eq!(field.vectors.as_ref().unwrap()[p], (2.25, -0.25));
+        assert!(field
+            .distances
+            .iter()
+            .all(|&d| d <= (2.5_f64.powi(2) + 5.0_f64.powi(2)).sqrt()));
+    }
+}
@@ -0,0 +1,238 @@
+// Polygonal domains with holes, restricting the points and cells to a region of the box.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use geo::BooleanOps;
+use rayon::prelude::*;
+
+use crate::cells::{signed_area, VoronoiCells};
+
+/// Polygon with holes, the exterior ring counterclockwise and the holes clockwise.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Polygon {
+    pub exterior: Vec<(f64, f64)>,
+    pub holes: Vec<Vec<(f64, f64)>>,
+}
+
+/// Returns whether `p` is inside `ring`, counting the edges crossed by a ray going right.
+fn ring_contains(ring: &[(f64, f64)], p: (f64, f64)) -> bool {
+    let mut inside = false;
+    for (i, &a) in ring.iter().enumerate() {
+        let b = ring[(i + 1) % ring.len()];
+        if (a.1 > p.1) != (b.1 > p.1) {
+            let x = a.0 + (p.1 - a.1) / (b.1 - a.1) * (b.0 - a.0);
+            if x > p.0 {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+fn ring_to_geo(ring: &[(f64, f64)]) -> geo::LineString<f64> {
+    ring.iter()
+        .map(|&(x, y)| geo::coord! { x: x, y: y })
+        .collect()
+}
+
+/// Drops the closing vertex of a `geo` ring.
+fn ring_from_geo(ring: &geo::LineString<f64>) -> Vec<(f64, f64)> {
+    let mut ring: Vec<(f64, f64)> = ring.coords().map(|c| (c.x, c.y)).collect();
+    if ring.len() > 1 && ring.first() == ring.last() {
+        ring.pop();
+    }
+    ring
+}
+
+impl Polygon {
+    /// Builds a polygon from its rings, fixing their orientation.
+    pub fn new(
+        mut exterior: Vec<(f64, f64)>,
+        mut holes: Vec<Vec<(f64, f64)>>,
+    ) -> Result<Polygon, &'static str> {
+        if exterior.len() < 3 || holes.iter().any(|hole| hole.len() < 3) {
+            return Err("Polygon rings need at least 3 vertices");
+        }
+        if signed_area(&exterior) < 0.0 {
+            exterior.reverse();
+        }
+        for hole in holes.iter_mut() {
+            if signed_area(hole) > 0.0 {
+                hole.reverse();
+            }
+        }
+        Ok(Polygon { exterior, holes })
+    }
+
+    /// Reads one `x,y` or `x y` vertex per line, rings being separated by empty lines.
+    ///
+    /// The first ring is the exterior and the next ones are holes. Lines starting with `#` are
+    /// comments.
+    pub fn read(path: &Path) -> Result<Polygon, &'static str> {
+        let file = File::open(path).map_err(|_| "Unable to open the domain file")?;
+
+        let mut rings: Vec<Vec<(f64, f64)>> = vec![Vec::new()];
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|_| "Unable to read the domain file")?;
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            if line.is_empty() {
+                if !rings.last().unwrap().is_empty() {
+                    rings.push(Vec::new());
+                }
+                continue;
+            }
+            let values = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|value| !value.is_empty())
+                .map(str::parse::<f64>)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| "Domain file contains a value which isn't a number")?;
+            match values[..] {
+                [x, y] => rings.last_mut().unwrap().push((x, y)),
+                _ => return Err("Domain file lines must hold 2 coordinates"),
+            }
+        }
+        if rings.last().unwrap().is_empty() {
+            rings.pop();
+        }
+
+        let mut rings = rings.into_iter();
+        let exterior = rings.next().ok_or("Domain file is empty")?;
+        Polygon::new(exterior, rings.collect())
+    }
+
+    /// Returns whether `p` is inside the exterior ring and outside the holes.
+    pub fn contains(&self, p: (f64, f64)) -> bool {
+        ring_contains(&self.exterior, p) && !self.holes.iter().any(|hole| ring_contains(hole, p))
+    }
+
+    pub fn area(&self) -> f64 {
+        signed_area(&self.exterior) + self.holes.iter().map(|hole| signed_area(hole)).sum::<f64>()
+    }
+
+    /// Returns the `(min, max)` corners of the box around the polygon.
+    pub fn bounding_box(&self) -> ((f64, f64), (f64, f64)) {
+        self.exterior.iter().fold(
+            ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN)),
+            |(min, max), &(x, y)| ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y))),
+        )
+    }
+
+    /// Labels the pixels whose center is outside the polygon with 0.
+    pub fn mask(&self, pixels: &mut [usize], reso: (usize, usize), config: (f64, f64)) {
+        let pixel_size = (config.0 / reso.0 as f64, config.1 / reso.1 as f64);
+        pixels
+            .par_chunks_mut(reso.0)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, label) in row.iter_mut().enumerate() {
+                    let center = (
+                        (x as f64 + 0.5) * pixel_size.0,
+                        (y as f64 + 0.5) * pixel_size.1,
+                    );
+                    if !self.contains(center) {
+                        *label = 0;
+                    }
+                }
+            });
+    }
+
+    pub(crate) fn to_geo(&self) -> geo::Polygon<f64> {
+        geo::Polygon::new(
+            ring_to_geo(&self.exterior),
+            self.holes.iter().map(|hole| ring_to_geo(hole)).collect(),
+        )
+    }
+
+    fn from_geo(polygon: &geo::Polygon<f64>) -> Result<Polygon, &'static str> {
+        Polygon::new(
+            ring_from_geo(polygon.exterior()),
+            polygon.interiors().iter().map(ring_from_geo).collect(),
+        )
+    }
+}
+
+/// Clips each cell to the domain.
+///
+/// A cell may be split by the domain into several polygons, hold some of its holes, or vanish
+/// when it is outside.
+pub fn clip_cells(cells: &VoronoiCells, domain: &Polygon) -> Vec<Vec<Polygon>> {
+    let domain = domain.to_geo();
+    (0..cells.cells.len())
+        .into_par_iter()
+        .map(|i| {
+            let polygon = cells.polygon(i);
+            if polygon.len() < 3 {
+                return Vec::new();
+            }
+            let cell = geo::Polygon::new(ring_to_geo(&polygon), Vec::new());
+            // Degenerate pieces, with less than 3 vertices, are dropped
+            cell.intersection(&domain)
+                .iter()
+                .filter_map(|piece| Polygon::from_geo(piece).ok())
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 10*10 square with a 2*2 hole in the middle.
+    fn square_with_hole() -> Polygon {
+        Polygon::new(
+            vec![(0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0)],
+            vec![vec![(4.0, 4.0), (6.0, 4.0), (6.0, 6.0), (4.0, 6.0)]],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_read_and_contains() {
+        let path = std::env::temp_dir().join("blue_noise_test_domain.txt");
+        std::fs::write(
+            &path,
+            "# square with a hole\n0,0\n10,0\n10,10\n0,10\n\n4 4\n4 6\n6 6\n6 4\n",
+        )
+        .unwrap();
+        let domain = Polygon::read(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(domain.holes.len(), 1);
+        assert_eq!(domain.area(), 96.0);
+        assert!(domain.contains((1.0, 1.0)));
+        assert!(!domain.contains((5.0, 5.0)));
+        assert!(!domain.contains((11.0, 5.0)));
+        assert_eq!(domain.bounding_box(), ((0.0, 0.0), (10.0, 10.0)));
+    }
+
+    #[test]
+    fn test_clip_cells() {
+        // Left and right halves of the square, the hole splits its area between them
+        let cells = VoronoiCells {
+            vertices: vec![
+                (0.0, 0.0),
+                (5.0, 0.0),
+                (10.0, 0.0),
+                (0.0, 10.0),
+                (5.0, 10.0),
+                (10.0, 10.0),
+            ],
+            cells: vec![vec![0, 1, 4, 3], vec![1, 2, 5, 4]],
+            period: None,
+        };
+
+        let clipped = clip_cells(&cells, &square_with_hole());
+
+        for pieces in &clipped {
+            assert_eq!(pieces.len(), 1);
+            assert!((pieces[0].area() - 48.0).abs() < 1e-9);
+        }
+    }
+}
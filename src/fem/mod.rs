@@ -0,0 +1,197 @@
+// Meshes written for finite element codes, as Gmsh or Medit files.
+//
+// Neither format has general polygons: triangles and quadrilaterals are written as they are, and
+// larger polygons, which are convex Voronoi cells, are split into fans of triangles. Elements
+// keep the index of their cell as elementary tag (Gmsh) or reference (Medit), and boundary edges
+// are tagged with the side of the box they lie on: 1 bottom, 2 right, 3 top and 4 left.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::delaunay::Side;
+use crate::vtk::Mesh;
+
+/// Tag of the elements covering the box, following the side tags.
+const DOMAIN_TAG: usize = 5;
+
+fn side_tag(side: Side) -> usize {
+    match side {
+        Side::Bottom => 1,
+        Side::Right => 2,
+        Side::Top => 3,
+        Side::Left => 4,
+    }
+}
+
+/// Triangles and quadrilaterals of a mesh, with the index of their cell.
+struct Elements {
+    triangles: Vec<([usize; 3], usize)>,
+    quadrilaterals: Vec<([usize; 4], usize)>,
+}
+
+fn elements(mesh: &Mesh) -> Elements {
+    let mut triangles = Vec::new();
+    let mut quadrilaterals = Vec::new();
+    for (i, cell) in mesh.cells.iter().enumerate() {
+        match cell[..] {
+            [a, b, c] => triangles.push(([a, b, c], i)),
+            [a, b, c, d] => quadrilaterals.push(([a, b, c, d], i)),
+            _ if cell.len() > 4 => {
+                for j in 1..cell.len() - 1 {
+                    triangles.push(([cell[0], cell[j], cell[j + 1]], i));
+                }
+            }
+            // Vertices and degenerate cells cover no area
+            _ => {}
+        }
+    }
+    Elements {
+        triangles,
+        quadrilaterals,
+    }
+}
+
+/// Writes an ASCII Gmsh 2.2 file, with physical groups for the box sides and the domain.
+pub fn write_gmsh(mesh: &Mesh, path: &Path) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "$MeshFormat\n2.2 0 8\n$EndMeshFormat")?;
+    writeln!(file, "$PhysicalNames\n5")?;
+    for (tag, name) in ["bottom", "right", "top", "left"].iter().enumerate() {
+        writeln!(file, "1 {} \"{}\"", tag + 1, name)?;
+    }
+    writeln!(file, "2 {} \"domain\"", DOMAIN_TAG)?;
+    writeln!(file, "$EndPhysicalNames")?;
+
+    writeln!(file, "$Nodes\n{}", mesh.points.len())?;
+    for (i, (x, y)) in mesh.points.iter().enumerate() {
+        writeln!(file, "{} {} {} 0", i + 1, x, y)?;
+    }
+    writeln!(file, "$EndNodes")?;
+
+    // Element types: 1 line, 2 triangle, 3 quadrilateral
+    let Elements {
+        triangles,
+        quadrilaterals,
+    } = elements(mesh);
+    let count = mesh.boundary_edges.len() + triangles.len() + quadrilaterals.len();
+    writeln!(file, "$Elements\n{}", count)?;
+    let mut id = 0;
+    for &(a, b, side) in &mesh.boundary_edges {
+        id += 1;
+        let tag = side_tag(side);
+        writeln!(file, "{} 1 2 {} {} {} {}", id, tag, tag, a + 1, b + 1)?;
+    }
+    for ([a, b, c], cell) in triangles {
+        id += 1;
+        writeln!(
+            file,
+            "{} 2 2 {} {} {} {} {}",
+            id,
+            DOMAIN_TAG,
+            cell + 1,
+            a + 1,
+            b + 1,
+            c + 1
+        )?;
+    }
+    for ([a, b, c, d], cell) in quadrilaterals {
+        id += 1;
+        writeln!(
+            file,
+            "{} 3 2 {} {} {} {} {} {}",
+            id,
+            DOMAIN_TAG,
+            cell + 1,
+            a + 1,
+            b + 1,
+            c + 1,
+            d + 1
+        )?;
+    }
+    writeln!(file, "$EndElements")?;
+    file.flush()
+}
+
+/// Writes an ASCII Medit file, boundary edges referencing their side and elements their cell.
+pub fn write_medit(mesh: &Mesh, path: &Path) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "MeshVersionFormatted 2\n\nDimension 2\n")?;
+
+    writeln!(file, "Vertices\n{}", mesh.points.len())?;
+    for (x, y) in &mesh.points {
+        writeln!(file, "{} {} 0", x, y)?;
+    }
+
+    if !mesh.boundary_edges.is_empty() {
+        writeln!(file, "\nEdges\n{}", mesh.boundary_edges.len())?;
+        for &(a, b, side) in &mesh.boundary_edges {
+            writeln!(file, "{} {} {}", a + 1, b + 1, side_tag(side))?;
+        }
+    }
+
+    let Elements {
+        triangles,
+        quadrilaterals,
+    } = elements(mesh);
+    if !triangles.is_empty() {
+        writeln!(file, "\nTriangles\n{}", triangles.len())?;
+        for ([a, b, c], cell) in triangles {
+            writeln!(file, "{} {} {} {}", a + 1, b + 1, c + 1, cell + 1)?;
+        }
+    }
+    if !quadrilaterals.is_empty() {
+        writeln!(file, "\nQuadrilaterals\n{}", quadrilaterals.len())?;
+        for ([a, b, c, d], cell) in quadrilaterals {
+            writeln!(file, "{} {} {} {} {}", a + 1, b + 1, c + 1, d + 1, cell + 1)?;
+        }
+    }
+
+    writeln!(file, "\nEnd")?;
+    file.flush()
+}
+
+/// Writes a Medit file when `path` ends with `.mesh`, and a Gmsh file otherwise.
+pub fn write(mesh: &Mesh, path: &Path) -> std::io::Result<()> {
+    let medit = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("mesh"));
+    if medit {
+        write_medit(mesh, path)
+    } else {
+        write_gmsh(mesh, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_medit() {
+        // A pentagon, split into 3 triangles, next to a square
+        let mesh = Mesh {
+            points: vec![
+                (0.0, 0.0),
+                (1.0, 0.0),
+                (2.0, 0.0),
+                (2.0, 1.0),
+                (1.0, 1.0),
+                (0.5, 1.5),
+                (0.0, 1.0),
+            ],
+            cells: vec![vec![0, 1, 4, 5, 6], vec![1, 2, 3, 4]],
+            cell_data: Vec::new(),
+            boundary_edges: vec![(0, 1, Side::Bottom), (2, 3, Side::Right)],
+        };
+
+        let path = std::env::temp_dir().join("blue_noise_test_fem.mesh");
+        write(&mesh, &path).unwrap();
+        let medit = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert!(medit.contains("Edges\n2\n1 2 1\n3 4 2\n"));
+        assert!(medit.contains("Triangles\n3\n1 2 5 1\n1 5 6 1\n1 6 7 1\n"));
+        assert!(medit.contains("Quadrilaterals\n1\n2 3 4 5 2\n"));
+    }
+}
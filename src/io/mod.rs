@@ -0,0 +1,305 @@
+// Reading and writing point lists.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Points with their optional power diagram weights.
+pub type WeightedPoints = (Vec<(f64, f64)>, Option<Vec<f64>>);
+
+/// Points with their optional weights, in 3D, as read from a point cloud.
+pub type PointCloud = (Vec<(f64, f64, f64)>, Option<Vec<f64>>);
+
+/// Reads the numbers of each non-empty line, separated by commas or whitespace.
+fn read_rows(path: &Path, comma: bool) -> Result<Vec<Vec<f64>>, &'static str> {
+    let file = File::open(path).map_err(|_| "Unable to open the points file")?;
+
+    let mut rows = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|_| "Unable to read the points file")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let values: Result<Vec<f64>, _> = if comma {
+            line.split(',').map(|value| value.trim().parse()).collect()
+        } else {
+            line.split_whitespace().map(str::parse).collect()
+        };
+        rows.push(values.map_err(|_| "Points file contains a value which isn't a number")?);
+    }
+    Ok(rows)
+}
+
+/// Reads one `x,y` or `x,y,w` point per line, as written by `write_points_csv`.
+///
+/// Either every point has a weight or none has. Empty lines are skipped.
+pub fn read_points_csv(path: &Path) -> Result<WeightedPoints, &'static str> {
+    let mut points = Vec::new();
+    let mut weights = Vec::new();
+    for values in read_rows(path, true)? {
+        match values[..] {
+            [x, y] => points.push((x, y)),
+            [x, y, w] => {
+                points.push((x, y));
+                weights.push(w);
+            }
+            _ => return Err("Points file lines must be `x,y` or `x,y,w`"),
+        }
+    }
+
+    match weights.len() {
+        0 => Ok((points, None)),
+        n if n == points.len() => Ok((points, Some(weights))),
+        _ => Err("Either all points or none must have a weight"),
+    }
+}
+
+/// Reads one `x,y,z` point per line, as written by `write_points_csv_3d`.
+pub fn read_points_csv_3d(path: &Path) -> Result<Vec<(f64, f64, f64)>, &'static str> {
+    read_rows(path, true)?
+        .into_iter()
+        .map(|values| match values[..] {
+            [x, y, z] => Ok((x, y, z)),
+            _ => Err("Points file lines must be `x,y,z`"),
+        })
+        .collect()
+}
+
+/// Reads one `x y z` point per line, separated by whitespace.
+pub fn read_points_xyz(path: &Path) -> Result<Vec<(f64, f64, f64)>, &'static str> {
+    read_rows(path, false)?
+        .into_iter()
+        .map(|values| match values[..] {
+            [x, y, z] => Ok((x, y, z)),
+            _ => Err("XYZ file lines must be `x y z`"),
+        })
+        .collect()
+}
+
+/// Reads a scalar of the PLY `kind` from a binary file.
+fn read_ply_scalar<R: Read>(
+    reader: &mut R,
+    kind: &str,
+    big_endian: bool,
+) -> Result<f64, &'static str> {
+    let size = match kind {
+        "char" | "int8" | "uchar" | "uint8" => 1,
+        "short" | "int16" | "ushort" | "uint16" => 2,
+        "int" | "int32" | "uint" | "uint32" | "float" | "float32" => 4,
+        "double" | "float64" => 8,
+        _ => return Err("PLY file has an unknown property type"),
+    };
+    let mut bytes = [0; 8];
+    reader
+        .read_exact(&mut bytes[..size])
+        .map_err(|_| "PLY file is truncated")?;
+    if big_endian {
+        bytes[..size].reverse();
+    }
+    let value = match kind {
+        "char" | "int8" => bytes[0] as i8 as f64,
+        "uchar" | "uint8" => bytes[0] as f64,
+        "short" | "int16" => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        "ushort" | "uint16" => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        "int" | "int32" => i32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+        "uint" | "uint32" => u32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+        "float" | "float32" => f32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+        _ => f64::from_le_bytes(bytes),
+    };
+    Ok(value)
+}
+
+/// Reads the vertices of an ASCII or binary PLY file, with their `weight` property if any.
+///
+/// The vertex element must come first, and only its `x`, `y`, `z` and `weight` properties are
+/// kept.
+pub fn read_points_ply(path: &Path) -> Result<PointCloud, &'static str> {
+    let file = File::open(path).map_err(|_| "Unable to open the points file")?;
+    let mut reader = BufReader::new(file);
+    let mut line = String::new();
+    let mut next_line = |reader: &mut BufReader<File>| -> Result<String, &'static str> {
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(_) => Err("PLY header is truncated"),
+            Ok(_) => Ok(line.trim().to_string()),
+        }
+    };
+
+    if next_line(&mut reader)? != "ply" {
+        return Err("Not a PLY file");
+    }
+    let mut format = String::new();
+    let mut count = None;
+    let mut in_vertex = false;
+    // `(type, name)` of the vertex properties
+    let mut properties: Vec<(String, String)> = Vec::new();
+    loop {
+        let header = next_line(&mut reader)?;
+        let words: Vec<&str> = header.split_whitespace().collect();
+        match words[..] {
+            ["end_header"] => break,
+            ["format", kind, _] => format = kind.to_string(),
+            ["element", "vertex", n] if count.is_none() => {
+                count = Some(
+                    n.parse::<usize>()
+                        .map_err(|_| "PLY vertex count is invalid")?,
+                );
+                in_vertex = true;
+            }
+            ["element", ..] if count.is_none() => {
+                return Err("PLY files must start with the vertex element")
+            }
+            ["element", ..] => in_vertex = false,
+            ["property", "list", ..] if in_vertex => {
+                return Err("PLY vertices can't have list properties")
+            }
+            ["property", kind, name] if in_vertex => {
+                properties.push((kind.to_string(), name.to_string()))
+            }
+            _ => {}
+        }
+    }
+    let count = count.ok_or("PLY file has no vertex element")?;
+    let index = |name: &str| properties.iter().position(|(_, n)| n == name);
+    let (x, y, z) = match (index("x"), index("y"), index("z")) {
+        (Some(x), Some(y), z) => (x, y, z),
+        _ => return Err("PLY vertices need x and y properties"),
+    };
+    let weight = index("weight");
+
+    let mut points = Vec::with_capacity(count);
+    let mut weights = Vec::new();
+    for _ in 0..count {
+        let values: Vec<f64> = match format.as_str() {
+            "ascii" => {
+                let row = next_line(&mut reader).map_err(|_| "PLY file is truncated")?;
+                row.split_whitespace()
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| "PLY file contains a value which isn't a number")?
+            }
+            "binary_little_endian" | "binary_big_endian" => properties
+                .iter()
+                .map(|(kind, _)| read_ply_scalar(&mut reader, kind, format == "binary_big_endian"))
+                .collect::<Result<_, _>>()?,
+            _ => return Err("PLY file has an unknown format"),
+        };
+        if values.len() < properties.len() {
+            return Err("PLY vertex has too few values");
+        }
+        points.push((values[x], values[y], z.map_or(0.0, |z| values[z])));
+        if let Some(weight) = weight {
+            weights.push(values[weight]);
+        }
+    }
+
+    Ok((points, weight.map(|_| weights)))
+}
+
+/// Reads points from a CSV, XYZ or PLY file depending on its extension, keeping `(x, y)`.
+///
+/// XYZ and PLY clouds must lie in the `z = 0` plane.
+pub fn read_points(path: &Path) -> Result<WeightedPoints, &'static str> {
+    let (points, weights) = match extension(path).as_str() {
+        "xyz" => (read_points_xyz(path)?, None),
+        "ply" => read_points_ply(path)?,
+        _ => return read_points_csv(path),
+    };
+    if points.iter().any(|p| p.2 != 0.0) {
+        return Err("Points must lie in the z = 0 plane to be used in 2D");
+    }
+    Ok((points.iter().map(|p| (p.0, p.1)).collect(), weights))
+}
+
+/// Reads points from a CSV, XYZ or PLY file depending on its extension.
+pub fn read_points_3d(path: &Path) -> Result<Vec<(f64, f64, f64)>, &'static str> {
+    match extension(path).as_str() {
+        "xyz" => read_points_xyz(path),
+        "ply" => Ok(read_points_ply(path)?.0),
+        _ => read_points_csv_3d(path),
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Writes one `x,y` line per point, or `x,y,w` lines when weights are given.
+pub fn write_points_csv(
+    path: &Path,
+    points: &[(f64, f64)],
+    weights: Option<&[f64]>,
+) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    for (i, (x, y)) in points.iter().enumerate() {
+        match weights {
+            Some(weights) => writeln!(file, "{},{},{}", x, y, weights[i])?,
+            None => writeln!(file, "{},{}", x, y)?,
+        }
+    }
+    file.flush()
+}
+
+/// Writes one `x,y,z` line per point.
+pub fn write_points_csv_3d(path: &Path, points: &[(f64, f64, f64)]) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    for (x, y, z) in points {
+        writeln!(file, "{},{},{}", x, y, z)?;
+    }
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let path = std::env::temp_dir().join("blue_noise_test_round_trip.csv");
+        let points = vec![(1.5, 2.0), (0.25, 7.0)];
+        let weights = vec![0.5, -1.0];
+
+        write_points_csv(&path, &points, Some(&weights)).unwrap();
+        assert_eq!(
+            read_points_csv(&path).unwrap(),
+            (points.clone(), Some(weights))
+        );
+
+        write_points_csv(&path, &points, None).unwrap();
+        assert_eq!(read_points_csv(&path).unwrap(), (points, None));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_read_ply() {
+        let path = std::env::temp_dir().join("blue_noise_test_cloud.ply");
+        let header = "ply\nformat {} 1.0\nelement vertex 2\nproperty float x\nproperty float y\n\
+                      property float z\nproperty double weight\nelement face 0\n\
+                      property list uchar int vertex_indices\nend_header\n";
+
+        std::fs::write(
+            &path,
+            header.replace("{}", "ascii") + "1.5 2 0 0.5\n0.25 7 0 -1\n",
+        )
+        .unwrap();
+        let ascii = read_points(&path).unwrap();
+
+        let mut binary = header.replace("{}", "binary_little_endian").into_bytes();
+        for (x, y, w) in [(1.5_f32, 2.0_f32, 0.5_f64), (0.25, 7.0, -1.0)] {
+            for value in [x, y, 0.0] {
+                binary.extend(value.to_le_bytes());
+            }
+            binary.extend(w.to_le_bytes());
+        }
+        std::fs::write(&path, binary).unwrap();
+        let binary = read_points(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let expected = (vec![(1.5, 2.0), (0.25, 7.0)], Some(vec![0.5, -1.0]));
+        assert_eq!(ascii, expected);
+        assert_eq!(binary, expected);
+    }
+}
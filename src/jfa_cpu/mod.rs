@@ -1,91 +1,436 @@
-static RESO: usize = 512;
+use rayon::prelude::*;
 
-fn jfa_step(pixel_grid: &mut [usize], normal_points: &[(usize, usize)], k: usize) {
-    for x in 0..RESO {
-        for y in 0..RESO {
-            let initial_poisition = x + y * RESO;
-            // Check the 8-neighborhood (jump in all directions) and update to the closest point
-            for dx in [-1, 0, 1] {
-                for dy in [-1, 0, 1] {
-                    let new_x = x as isize + dx * k as isize;
-                    let new_y = y as isize + dy * k as isize;
-
-                    if !(new_x >= 0 && new_x < RESO as isize && new_y >= 0 && new_y < RESO as isize)
-                    {
-                        continue;
-                    }
-
-                    let new_position = (new_x as usize) + (new_y as usize) * RESO;
-                    let found_color = pixel_grid[new_position];
-                    let current_color = pixel_grid[initial_poisition];
-
-                    if (dx == 0 && dy == 0) || found_color == 0 || current_color == found_color {
-                        continue;
-                    }
-
-                    if current_color == 0 {
-                        pixel_grid[initial_poisition] = found_color;
-                        continue;
-                    }
-
-                    // we're now in the case where we have two colors distinct colors
-                    // so we'll assign the closest color to the current pixel
-                    let point1 = normal_points[current_color - 1];
-                    let point2 = normal_points[found_color - 1];
-
-                    let dist1 = ((x as isize - point1.0 as isize).pow(2)
-                        + (y as isize - point1.1 as isize).pow(2))
-                        as f64;
-                    let dist2 = ((x as isize - point2.0 as isize).pow(2)
-                        + (y as isize - point2.1 as isize).pow(2))
-                        as f64;
-
-                    //dbg!(point1, point2, (x,y), dist1, dist2);
-
-                    if dist2 < dist1 {
-                        pixel_grid[initial_poisition] = found_color;
-                    }
-                }
+use crate::metric::Metric;
+use crate::periodic::nearest_image;
+
+/// Seeds snapped to the grid, and how to compare their distances.
+struct JfaSetup {
+    normal_points: Vec<(usize, usize)>,
+    /// Power diagram weights, all zero for a plain Voronoi diagram
+    weights: Vec<f64>,
+    reso: (usize, usize),
+    /// Size of a pixel in domain units
+    pixel_size: (f64, f64),
+    metric: Metric,
+    /// Whether the grid wraps around, opposite edges being neighbors
+    periodic: bool,
+}
+
+impl JfaSetup {
+    /// Returns the power distance `d² - w` from the pixel `(x, y)` to the seed with `color`.
+    fn power_distance(&self, (x, y): (usize, usize), color: usize) -> f64 {
+        let point = self.normal_points[color - 1];
+        let mut d = (x as f64 - point.0 as f64, y as f64 - point.1 as f64);
+        if self.periodic {
+            // Nearest image of the seed
+            d = nearest_image(d, (self.reso.0 as f64, self.reso.1 as f64));
+        }
+        let (dx, dy) = d;
+
+        // Distances are measured in domain units, so that pixels which aren't
+        // square don't skew the comparison
+        let distance = self
+            .metric
+            .distance(dx * self.pixel_size.0, dy * self.pixel_size.1);
+        distance * distance - self.weights[color - 1]
+    }
+}
+
+/// Returns the color of the pixel `(x, y)` after looking at its 8 neighbors `k` pixels away.
+fn closest_color(
+    pixel_grid: &[usize],
+    setup: &JfaSetup,
+    (x, y): (usize, usize),
+    k: usize,
+) -> usize {
+    let (reso_x, reso_y) = setup.reso;
+    let mut current_color = pixel_grid[x + y * reso_x];
+
+    // Check the 8-neighborhood (jump in all directions) and update to the closest point
+    for dx in [-1, 0, 1] {
+        for dy in [-1, 0, 1] {
+            let mut new_x = x as isize + dx * k as isize;
+            let mut new_y = y as isize + dy * k as isize;
+
+            if setup.periodic {
+                new_x = new_x.rem_euclid(reso_x as isize);
+                new_y = new_y.rem_euclid(reso_y as isize);
+            } else if !(new_x >= 0
+                && new_x < reso_x as isize
+                && new_y >= 0
+                && new_y < reso_y as isize)
+            {
+                continue;
+            }
+
+            let new_position = (new_x as usize) + (new_y as usize) * reso_x;
+            let found_color = pixel_grid[new_position];
+
+            if (dx == 0 && dy == 0) || found_color == 0 || current_color == found_color {
+                continue;
+            }
+
+            if current_color == 0 {
+                current_color = found_color;
+                continue;
+            }
+
+            // we're now in the case where we have two colors distinct colors
+            // so we'll assign the closest color to the current pixel
+            let dist1 = setup.power_distance((x, y), current_color);
+            let dist2 = setup.power_distance((x, y), found_color);
+
+            if dist2 < dist1 {
+                current_color = found_color;
             }
         }
     }
+
+    current_color
 }
 
-pub fn jfa(points: &[(f64, f64)], config: (f64, f64)) -> Result<Vec<usize>, &'static str> {
+fn jfa_step(pixel_grid: &mut [usize], setup: &JfaSetup, k: usize) {
+    let (reso_x, reso_y) = setup.reso;
+    for x in 0..reso_x {
+        for y in 0..reso_y {
+            pixel_grid[x + y * reso_x] = closest_color(pixel_grid, setup, (x, y), k);
+        }
+    }
+}
+
+/// Same as `jfa_step`, but reads from `src` and writes to `dst` so that rows can be updated
+/// concurrently.
+fn jfa_step_parallel(src: &[usize], dst: &mut [usize], setup: &JfaSetup, k: usize) {
+    dst.par_chunks_mut(setup.reso.0)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = closest_color(src, setup, (x, y), k);
+            }
+        });
+}
+
+/// Snaps the points to the grid and marks them with their color.
+fn init_pixel_grid(
+    points: &[(f64, f64)],
+    weights: Option<&[f64]>,
+    config: (f64, f64),
+    reso: (usize, usize),
+    metric: Metric,
+    periodic: bool,
+) -> Result<(JfaSetup, Vec<usize>), &'static str> {
+    let (reso_x, reso_y) = reso;
+    if reso_x == 0 || reso_y == 0 {
+        return Err("JFA resolution must be greater than 0");
+    }
+    let weights = match weights {
+        Some(weights) if weights.len() != points.len() => {
+            return Err("Number of weights doesn't match the number of points")
+        }
+        Some(weights) => weights.to_vec(),
+        None => vec![0.0; points.len()],
+    };
+    let pixel_size = (config.0 / reso_x as f64, config.1 / reso_y as f64);
+
     let normal_points: Vec<(usize, usize)> = points
         .iter()
         .map(|(a, b)| {
-            let x = ((a * RESO as f64 / config.0).min(RESO as f64 - 1.0)) as usize;
-            let y = ((b * RESO as f64 / config.1).min(RESO as f64 - 1.0)) as usize;
+            let x = ((a * reso_x as f64 / config.0).min(reso_x as f64 - 1.0)) as usize;
+            let y = ((b * reso_y as f64 / config.1).min(reso_y as f64 - 1.0)) as usize;
             (x, y)
         })
         .collect();
 
-    let mut pixel_grid = vec![0; RESO * RESO];
+    let mut pixel_grid = vec![0; reso_x * reso_y];
 
     // Mark the initial points on the grid with their respective color
     for (i, point) in normal_points.iter().enumerate() {
         let color = i + 1; // 0 means uncolored
-        pixel_grid[point.0 + point.1 * RESO] = color;
+        pixel_grid[point.0 + point.1 * reso_x] = color;
     }
 
-    // Main JFA loop
-    let now = std::time::Instant::now();
+    let setup = JfaSetup {
+        normal_points,
+        weights,
+        reso,
+        pixel_size,
+        metric,
+        periodic,
+    };
+    Ok((setup, pixel_grid))
+}
+
+/// Labels each pixel with its nearest seed, `i + 1` for the seed `i`.
+///
+/// With `weights`, pixels go to the seed minimizing the power distance `d² - w`, which gives a
+/// power diagram (Laguerre cells) instead of a Voronoi diagram. With `periodic`, the box is a
+/// torus and seeds are also found across opposite edges.
+pub fn jfa(
+    points: &[(f64, f64)],
+    weights: Option<&[f64]>,
+    config: (f64, f64),
+    reso: (usize, usize),
+    metric: Metric,
+    periodic: bool,
+) -> Result<Vec<usize>, &'static str> {
+    let (setup, mut pixel_grid) = init_pixel_grid(points, weights, config, reso, metric, periodic)?;
 
-    let mut k = (RESO / 2).max(1);
-    jfa_step(&mut pixel_grid, &normal_points, 1); // 1+JFA for more precision
+    // Main JFA loop
+    let mut k = (reso.0.max(reso.1) / 2).max(1);
+    jfa_step(&mut pixel_grid, &setup, 1); // 1+JFA for more precision
     while k >= 1 {
         //println!("Entering loop with k = {}", k);
-        jfa_step(&mut pixel_grid, &normal_points, k);
+        jfa_step(&mut pixel_grid, &setup, k);
         k /= 2;
     }
 
-    let elapsed = now.elapsed();
-    println!("{:.2?}", elapsed);
+    Ok(pixel_grid)
+}
+
+/// Multi-threaded JFA, each step reads from one grid and writes to the other (ping-pong).
+pub fn jfa_parallel(
+    points: &[(f64, f64)],
+    weights: Option<&[f64]>,
+    config: (f64, f64),
+    reso: (usize, usize),
+    metric: Metric,
+    periodic: bool,
+) -> Result<Vec<usize>, &'static str> {
+    let (setup, mut pixel_grid) = init_pixel_grid(points, weights, config, reso, metric, periodic)?;
+    let mut back_grid = vec![0; pixel_grid.len()];
+
+    // Main JFA loop
+    let mut steps = vec![1]; // 1+JFA for more precision
+    let mut k = (reso.0.max(reso.1) / 2).max(1);
+    while k >= 1 {
+        steps.push(k);
+        k /= 2;
+    }
+
+    for k in steps {
+        jfa_step_parallel(&pixel_grid, &mut back_grid, &setup, k);
+        std::mem::swap(&mut pixel_grid, &mut back_grid);
+    }
 
     Ok(pixel_grid)
 }
 
+/// Seeds snapped to the voxel grid of a 3D JFA.
+struct JfaSetup3d {
+    normal_points: Vec<(usize, usize, usize)>,
+    reso: (usize, usize, usize),
+    /// Size of a voxel in domain units
+    voxel_size: (f64, f64, f64),
+}
+
+impl JfaSetup3d {
+    /// Returns the squared Euclidean distance from the voxel `(x, y, z)` to the seed with `color`.
+    fn distance(&self, (x, y, z): (usize, usize, usize), color: u32) -> f64 {
+        let point = self.normal_points[color as usize - 1];
+        let dx = (x as f64 - point.0 as f64) * self.voxel_size.0;
+        let dy = (y as f64 - point.1 as f64) * self.voxel_size.1;
+        let dz = (z as f64 - point.2 as f64) * self.voxel_size.2;
+        dx * dx + dy * dy + dz * dz
+    }
+}
+
+/// Number of voxels in the slabs a 3D JFA step is computed by, bounding the memory used on top
+/// of the label volume (16 MiB per slab).
+const SLAB_VOXELS: usize = 1 << 22;
+
+/// Splits the layers of the 3D JFA step `k` into chunks of at most `slab_layers` layers, as
+/// `(first layer, layer count)`.
+///
+/// A layer only reads the layers `k` below and above it, so the chunks follow the chains of
+/// layers `k` apart: the chunk right before one starting at `z >= k` is the chunk `k` layers
+/// below it, and the chunk `k` layers above it hasn't been updated yet.
+pub(crate) fn slab_chunks(
+    reso_z: usize,
+    k: usize,
+    slab_layers: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    let thickness = slab_layers.min(k).max(1);
+    (0..k.min(reso_z))
+        .step_by(thickness)
+        .flat_map(move |first| {
+            let width = thickness.min(k - first);
+            (first..reso_z)
+                .step_by(k)
+                .map(move |z| (z, width.min(reso_z - z)))
+        })
+}
+
+/// Returns the color of the voxel `(x, y, z)` after looking at its 26 neighbors `k` voxels away.
+///
+/// `windows` hold the labels of the chunk `k` layers below the voxel, of its own chunk and of
+/// the chunk `k` layers above, the voxel being in the layer `local_z` of its chunk.
+fn closest_color_3d(
+    windows: [&[u32]; 3],
+    setup: &JfaSetup3d,
+    (x, y, z): (usize, usize, usize),
+    local_z: usize,
+    k: usize,
+) -> u32 {
+    let (reso_x, reso_y, reso_z) = setup.reso;
+    let mut current_color = windows[1][x + (y + local_z * reso_y) * reso_x];
+    let mut current_distance = f64::MAX;
+    if current_color != 0 {
+        current_distance = setup.distance((x, y, z), current_color);
+    }
+
+    for (window, dz) in windows.into_iter().zip([-1, 0, 1]) {
+        for dy in [-1, 0, 1] {
+            for dx in [-1, 0, 1] {
+                let new_x = x as isize + dx * k as isize;
+                let new_y = y as isize + dy * k as isize;
+                let new_z = z as isize + dz * k as isize;
+                if (dx == 0 && dy == 0 && dz == 0)
+                    || !(0..reso_x as isize).contains(&new_x)
+                    || !(0..reso_y as isize).contains(&new_y)
+                    || !(0..reso_z as isize).contains(&new_z)
+                {
+                    continue;
+                }
+
+                let found_color =
+                    window[new_x as usize + (new_y as usize + local_z * reso_y) * reso_x];
+                if found_color == 0 || found_color == current_color {
+                    continue;
+                }
+                let distance = setup.distance((x, y, z), found_color);
+                if distance < current_distance {
+                    current_color = found_color;
+                    current_distance = distance;
+                }
+            }
+        }
+    }
+
+    current_color
+}
+
+/// Updates `voxel_grid` in place by chunks of at most `slab_layers` layers, one row per task
+/// when `parallel`. The old labels of the last chunk are kept for the chunk `k` layers above it.
+fn jfa_step_3d(
+    voxel_grid: &mut [u32],
+    setup: &JfaSetup3d,
+    k: usize,
+    slab_layers: usize,
+    parallel: bool,
+) {
+    let (reso_x, reso_y, reso_z) = setup.reso;
+    let layer = reso_x * reso_y;
+    let slab = slab_layers.min(k).min(reso_z) * layer;
+    let mut below = vec![0; slab];
+    let mut updated = vec![0; slab];
+
+    for (z0, layers) in slab_chunks(reso_z, k, slab_layers) {
+        let chunk = z0 * layer..(z0 + layers) * layer;
+        let above = (z0 + k).min(reso_z) * layer..(z0 + k + layers).min(reso_z) * layer;
+        let windows = [
+            if z0 >= k { &below[..chunk.len()] } else { &[] },
+            &voxel_grid[chunk.clone()],
+            &voxel_grid[above],
+        ];
+        let update_row = |(i, row): (usize, &mut [u32])| {
+            let (local_z, y) = (i / reso_y, i % reso_y);
+            for (x, voxel) in row.iter_mut().enumerate() {
+                *voxel = closest_color_3d(windows, setup, (x, y, z0 + local_z), local_z, k);
+            }
+        };
+        let updated = &mut updated[..chunk.len()];
+        if parallel {
+            updated
+                .par_chunks_mut(reso_x)
+                .enumerate()
+                .for_each(update_row);
+        } else {
+            updated.chunks_mut(reso_x).enumerate().for_each(update_row);
+        }
+
+        below[..chunk.len()].copy_from_slice(&voxel_grid[chunk.clone()]);
+        voxel_grid[chunk].copy_from_slice(updated);
+    }
+}
+
+/// Labels each voxel of the `config` box with its nearest seed for the Euclidean metric, `i + 1`
+/// for the seed `i`.
+///
+/// The labels are `u32` and updated in place in a single volume, each step going through slabs
+/// of at most `SLAB_VOXELS` voxels: 512 MiB for a 512³ grid, and two slabs on top.
+pub fn jfa_3d(
+    points: &[(f64, f64, f64)],
+    config: (f64, f64, f64),
+    reso: (usize, usize, usize),
+) -> Result<Vec<u32>, &'static str> {
+    run_jfa_3d(points, config, reso, SLAB_VOXELS, false)
+}
+
+/// Same as `jfa_3d`, updating the rows of each slab in parallel.
+pub fn jfa_3d_parallel(
+    points: &[(f64, f64, f64)],
+    config: (f64, f64, f64),
+    reso: (usize, usize, usize),
+) -> Result<Vec<u32>, &'static str> {
+    run_jfa_3d(points, config, reso, SLAB_VOXELS, true)
+}
+
+fn run_jfa_3d(
+    points: &[(f64, f64, f64)],
+    config: (f64, f64, f64),
+    reso: (usize, usize, usize),
+    slab_voxels: usize,
+    parallel: bool,
+) -> Result<Vec<u32>, &'static str> {
+    let (reso_x, reso_y, reso_z) = reso;
+    if reso_x == 0 || reso_y == 0 || reso_z == 0 {
+        return Err("JFA resolution must be greater than 0");
+    }
+    if points.len() >= u32::MAX as usize {
+        return Err("Too many points to label voxels");
+    }
+
+    let snap =
+        |v: f64, reso: usize, size: f64| ((v * reso as f64 / size).min(reso as f64 - 1.0)) as usize;
+    let setup = JfaSetup3d {
+        normal_points: points
+            .iter()
+            .map(|&(x, y, z)| {
+                (
+                    snap(x, reso_x, config.0),
+                    snap(y, reso_y, config.1),
+                    snap(z, reso_z, config.2),
+                )
+            })
+            .collect(),
+        reso,
+        voxel_size: (
+            config.0 / reso_x as f64,
+            config.1 / reso_y as f64,
+            config.2 / reso_z as f64,
+        ),
+    };
+
+    let mut voxel_grid = vec![0; reso_x * reso_y * reso_z];
+    for (i, point) in setup.normal_points.iter().enumerate() {
+        voxel_grid[point.0 + (point.1 + point.2 * reso_y) * reso_x] = i as u32 + 1;
+    }
+
+    let mut steps = vec![1]; // 1+JFA for more precision
+    let mut k = (reso_x.max(reso_y).max(reso_z) / 2).max(1);
+    while k >= 1 {
+        steps.push(k);
+        k /= 2;
+    }
+
+    let slab_layers = (slab_voxels / (reso_x * reso_y)).max(1);
+    for k in steps {
+        jfa_step_3d(&mut voxel_grid, &setup, k, slab_layers, parallel);
+    }
+
+    Ok(voxel_grid)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -95,9 +440,177 @@ mod tests {
         let points = vec![(1.0, 1.0)];
         let config = (2.0, 2.0);
 
-        let pixel_grid = jfa(&points, config).unwrap();
+        let reso = 512;
+
+        let pixel_grid = jfa(
+            &points,
+            None,
+            config,
+            (reso, reso),
+            Metric::Euclidean,
+            false,
+        )
+        .unwrap();
 
         assert_eq!(pixel_grid[12], 1);
-        assert_eq!(pixel_grid[512 * RESO / 2 + RESO / 2], 1);
+        assert_eq!(pixel_grid[512 * reso / 2 + reso / 2], 1);
+    }
+
+    #[test]
+    fn test_non_power_of_two_resolution() {
+        let points = vec![(0.5, 0.5), (1.5, 1.5)];
+        let config = (2.0, 2.0);
+        let reso = 100;
+
+        let pixel_grid = jfa(
+            &points,
+            None,
+            config,
+            (reso, reso),
+            Metric::Euclidean,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(pixel_grid.len(), reso * reso);
+        assert_eq!(pixel_grid[0], 1);
+        assert_eq!(pixel_grid[reso * reso - 1], 2);
+    }
+
+    #[test]
+    fn test_jfa_parallel() {
+        let points = vec![(1.0, 1.0), (3.5, 2.0), (8.0, 7.5), (2.0, 9.0), (6.0, 4.0)];
+        let config = (10.0, 10.0);
+        let reso = (97, 64);
+
+        let pixel_grid =
+            jfa_parallel(&points, None, config, reso, Metric::Euclidean, false).unwrap();
+
+        assert_eq!(pixel_grid.len(), reso.0 * reso.1);
+        assert!(pixel_grid
+            .iter()
+            .all(|color| (1..=points.len()).contains(color)));
+        // Seeds keep their own color
+        assert_eq!(pixel_grid[9 + 6 * reso.0], 1);
+        assert_eq!(pixel_grid[77 + 48 * reso.0], 3);
+    }
+
+    #[test]
+    fn test_manhattan_metric() {
+        // The pixel at (2, 8) is closer to (6, 3) for the Euclidean metric, but closer to
+        // (1, 1) for the Manhattan one
+        let points = vec![(1.0, 1.0), (6.0, 3.0)];
+        let config = (10.0, 10.0);
+        let reso = (10, 10);
+
+        let euclidean = jfa(&points, None, config, reso, Metric::Euclidean, false).unwrap();
+        let manhattan = jfa(&points, None, config, reso, Metric::Manhattan, false).unwrap();
+
+        assert_eq!(euclidean[2 + 8 * reso.0], 2);
+        assert_eq!(manhattan[2 + 8 * reso.0], 1);
+    }
+
+    #[test]
+    fn test_non_square_grid() {
+        // Two seeds on a 10*1 strip, the bisector sits at x = 3
+        let points = vec![(1.0, 0.5), (5.0, 0.5)];
+        let config = (10.0, 1.0);
+        let reso = (200, 20);
+
+        let pixel_grid = jfa(&points, None, config, reso, Metric::Euclidean, false).unwrap();
+
+        assert_eq!(pixel_grid.len(), reso.0 * reso.1);
+        for y in 0..reso.1 {
+            assert_eq!(pixel_grid[50 + y * reso.0], 1);
+            assert_eq!(pixel_grid[70 + y * reso.0], 2);
+        }
+    }
+
+    #[test]
+    fn test_power_diagram() {
+        // With weights 0 and 8, the radical axis of the two seeds moves from x = 3 to x = 2
+        let points = vec![(1.0, 0.5), (5.0, 0.5)];
+        let weights = vec![0.0, 8.0];
+        let config = (10.0, 1.0);
+        let reso = (200, 20);
+
+        let pixel_grid = jfa(
+            &points,
+            Some(&weights),
+            config,
+            reso,
+            Metric::Euclidean,
+            false,
+        )
+        .unwrap();
+
+        for y in 0..reso.1 {
+            assert_eq!(pixel_grid[30 + y * reso.0], 1);
+            assert_eq!(pixel_grid[50 + y * reso.0], 2);
+        }
+        assert!(jfa(
+            &points,
+            Some(&[1.0]),
+            config,
+            reso,
+            Metric::Euclidean,
+            false
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_periodic() {
+        // Pixels near the right edge are closer to the left seed across the edge
+        let points = vec![(1.0, 5.0), (5.0, 5.0)];
+        let config = (10.0, 10.0);
+        let reso = (100, 100);
+
+        let bounded = jfa(&points, None, config, reso, Metric::Euclidean, false).unwrap();
+        let periodic = jfa(&points, None, config, reso, Metric::Euclidean, true).unwrap();
+
+        assert_eq!(bounded[95 + 50 * reso.0], 2);
+        assert_eq!(periodic[95 + 50 * reso.0], 1);
+        assert_eq!(periodic[75 + 50 * reso.0], 2);
+    }
+
+    #[test]
+    fn test_jfa_3d() {
+        let points = vec![(1.0, 1.0, 1.0), (3.0, 1.0, 1.0), (2.0, 3.0, 2.5)];
+        let config = (4.0, 4.0, 3.0);
+        let reso = (40, 40, 30);
+
+        let voxel_grid = jfa_3d(&points, config, reso).unwrap();
+
+        assert_eq!(voxel_grid.len(), reso.0 * reso.1 * reso.2);
+        // Seeds sit on voxels, each voxel is labeled with its nearest seed up to ties
+        for z in 0..reso.2 {
+            for y in 0..reso.1 {
+                for x in 0..reso.0 {
+                    let center = (x as f64 * 0.1, y as f64 * 0.1, z as f64 * 0.1);
+                    let distances: Vec<f64> = points
+                        .iter()
+                        .map(|p| {
+                            (p.0 - center.0).powi(2)
+                                + (p.1 - center.1).powi(2)
+                                + (p.2 - center.2).powi(2)
+                        })
+                        .collect();
+                    let nearest = distances.iter().cloned().fold(f64::MAX, f64::min);
+                    let label = voxel_grid[x + (y + z * reso.1) * reso.0] as usize;
+                    assert!(distances[label - 1] - nearest < 1e-9);
+                }
+            }
+        }
+
+        assert_eq!(jfa_3d_parallel(&points, config, reso).unwrap(), voxel_grid);
+        // Slabs thinner than the steps, or not dividing them, give the same labels
+        for slab_layers in [1, 3, 7] {
+            let slab_voxels = slab_layers * reso.0 * reso.1;
+            assert_eq!(
+                run_jfa_3d(&points, config, reso, slab_voxels, true).unwrap(),
+                voxel_grid
+            );
+        }
     }
 }
@@ -1,20 +1,62 @@
-const RESO: usize = 512;
-
-pub async fn run(points: &[(f64, f64)], config: (f64, f64)) -> Vec<u32> {
+use crate::jfa_cpu::slab_chunks;
+use crate::metric::Metric;
+
+const WORKGROUP_SIZE: usize = 16;
+
+/// Workgroups of the 3D shader are `WORKGROUP_SIZE_3D³` voxels.
+const WORKGROUP_SIZE_3D: usize = 4;
+
+/// Number of voxels in the slabs a 3D step is dispatched by. The storage buffer holds four slabs
+/// (64 MiB of labels), within the 128 MiB binding size every adapter allows.
+const CHUNK_VOXELS: usize = 1 << 22;
+
+pub async fn run(
+    points: &[(f64, f64)],
+    weights: Option<&[f64]>,
+    config: (f64, f64),
+    reso: (usize, usize),
+    metric: Metric,
+    periodic: bool,
+) -> Result<Vec<u32>, &'static str> {
+    let (reso_x, reso_y) = reso;
+    let buffer_size = reso_x * reso_y * std::mem::size_of::<u32>();
     let context = WgpuContext::new(
-        RESO * RESO * std::mem::size_of::<u32>(),
+        wgpu::include_wgsl!("shader.wgsl"),
+        buffer_size,
+        buffer_size,
         points.len() * std::mem::size_of::<(u32, u32)>(),
+        points.len() * std::mem::size_of::<f32>(),
     )
-    .await;
+    .await?;
+
+    // Grid uniform: pixel counts, pixel size in domain units, then whether the grid wraps around
+    let grid = [
+        reso_x as u32,
+        reso_y as u32,
+        ((config.0 / reso_x as f64) as f32).to_bits(),
+        ((config.1 / reso_y as f64) as f32).to_bits(),
+        periodic as u32,
+        0,
+        0,
+        0,
+    ];
+    context
+        .queue
+        .write_buffer(&context.grid_buffer, 0, bytemuck::cast_slice(&grid));
+    context.queue.write_buffer(
+        &context.metric_buffer,
+        0,
+        bytemuck::cast_slice(&metric.to_uniform()),
+    );
 
-    let normal_points = init_normal_points(points, config);
+    let normal_points = init_normal_points(points, config, reso);
 
-    let mut local_buffer = vec![0; RESO * RESO];
+    let mut local_buffer = vec![0; reso_x * reso_y];
 
     // Mark the initial points on the grid with their respective color
     for (i, point) in normal_points.iter().enumerate() {
         let color = i + 1; // 0 means uncolored
-        local_buffer[point.0 as usize + point.1 as usize * RESO] = color as u32;
+        local_buffer[point.0 as usize + point.1 as usize * reso_x] = color as u32;
     }
 
     // Flatten normal_points
@@ -29,22 +71,31 @@ pub async fn run(points: &[(f64, f64)], config: (f64, f64)) -> Vec<u32> {
         bytemuck::cast_slice(&normal_points),
     );
 
-    let mut k = (RESO / 2).max(1) as u32;
+    // Power diagram weights, all zero for a plain Voronoi diagram
+    let weights: Vec<f32> = match weights {
+        Some(weights) => weights.iter().map(|&w| w as f32).collect(),
+        None => vec![0.0; points.len()],
+    };
+    context
+        .queue
+        .write_buffer(&context.weights, 0, bytemuck::cast_slice(&weights));
+
+    let mut k = (reso_x.max(reso_y) / 2).max(1) as u32;
 
     log::info!("Starting JFA iterations...");
 
-    jfa_step(&context, &mut local_buffer, 1).await;
+    jfa_step(&context, &mut local_buffer, 1, reso).await;
     while k >= 1 {
-        jfa_step(&context, &mut local_buffer, k).await;
+        jfa_step(&context, &mut local_buffer, k, reso).await;
         k /= 2;
     }
 
     log::info!("done!");
 
-    local_buffer
+    Ok(local_buffer)
 }
 
-async fn jfa_step(context: &WgpuContext, local_buffer: &mut [u32], k: u32) {
+async fn jfa_step(context: &WgpuContext, local_buffer: &mut [u32], k: u32, reso: (usize, usize)) {
     //log::info!("Dispatching JFA step with k = {}", k);
 
     context.queue.write_buffer(
@@ -67,7 +118,12 @@ async fn jfa_step(context: &WgpuContext, local_buffer: &mut [u32], k: u32) {
         });
         compute_pass.set_pipeline(&context.pipeline);
         compute_pass.set_bind_group(0, &context.bind_group, &[]);
-        compute_pass.dispatch_workgroups((RESO / 16) as u32, (RESO / 16) as u32, 1);
+        // Round up so that resolutions which aren't multiples of the workgroup size are covered
+        compute_pass.dispatch_workgroups(
+            reso.0.div_ceil(WORKGROUP_SIZE) as u32,
+            reso.1.div_ceil(WORKGROUP_SIZE) as u32,
+            1,
+        );
     }
 
     command_encoder.copy_buffer_to_buffer(
@@ -83,6 +139,7 @@ async fn jfa_step(context: &WgpuContext, local_buffer: &mut [u32], k: u32) {
     //TODO: don't get data until the end https://github.com/gfx-rs/wgpu/wiki/Do's-and-Dont's
     get_data(
         local_buffer,
+        0,
         &context.storage_buffer,
         &context.output_staging_buffer,
         &context.device,
@@ -91,8 +148,10 @@ async fn jfa_step(context: &WgpuContext, local_buffer: &mut [u32], k: u32) {
     .await;
 }
 
+/// Reads `output` back from `storage_buffer`, starting `offset` bytes in.
 async fn get_data<T: bytemuck::Pod>(
     output: &mut [T],
+    offset: u64,
     storage_buffer: &wgpu::Buffer,
     staging_buffer: &wgpu::Buffer,
     device: &wgpu::Device,
@@ -100,15 +159,10 @@ async fn get_data<T: bytemuck::Pod>(
 ) {
     let mut command_encoder =
         device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-    command_encoder.copy_buffer_to_buffer(
-        storage_buffer,
-        0,
-        staging_buffer,
-        0,
-        size_of_val(output) as u64,
-    );
+    let size = size_of_val(output) as u64;
+    command_encoder.copy_buffer_to_buffer(storage_buffer, offset, staging_buffer, 0, size);
     queue.submit(Some(command_encoder.finish()));
-    let buffer_slice = staging_buffer.slice(..);
+    let buffer_slice = staging_buffer.slice(..size);
     let (sender, receiver) = flume::bounded(1);
     buffer_slice.map_async(wgpu::MapMode::Read, move |r| sender.send(r).unwrap());
     device.poll(wgpu::Maintain::wait()).panic_on_timeout();
@@ -117,27 +171,204 @@ async fn get_data<T: bytemuck::Pod>(
     staging_buffer.unmap();
 }
 
-fn init_normal_points(points: &[(f64, f64)], config: (f64, f64)) -> Vec<(u32, u32)> {
+fn init_normal_points(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    reso: (usize, usize),
+) -> Vec<(u32, u32)> {
     points
         .iter()
         .map(|(a, b)| {
-            let x = ((a * RESO as f64 / config.0).min(RESO as f64 - 1.0)) as u32;
-            let y = ((b * RESO as f64 / config.1).min(RESO as f64 - 1.0)) as u32;
+            let x = ((a * reso.0 as f64 / config.0).min(reso.0 as f64 - 1.0)) as u32;
+            let y = ((b * reso.1 as f64 / config.1).min(reso.1 as f64 - 1.0)) as u32;
             (x, y)
         })
         .collect()
 }
 
-pub fn main(points: &[(f64, f64)], config: (f64, f64)) -> Result<Vec<usize>, &'static str> {
+pub fn main(
+    points: &[(f64, f64)],
+    weights: Option<&[f64]>,
+    config: (f64, f64),
+    reso: (usize, usize),
+    metric: Metric,
+    periodic: bool,
+) -> Result<Vec<usize>, &'static str> {
+    if reso.0 == 0 || reso.1 == 0 {
+        return Err("JFA resolution must be greater than 0");
+    }
+    if weights.is_some_and(|weights| weights.len() != points.len()) {
+        return Err("Number of weights doesn't match the number of points");
+    }
     /*     env_logger::builder()
     .filter_level(log::LevelFilter::Info)
     .format_timestamp_nanos()
     .init(); */
-    let a = pollster::block_on(run(points, config));
+    let a = pollster::block_on(run(points, weights, config, reso, metric, periodic))?;
 
     Ok(a.into_iter().map(|x| x as usize).collect())
 }
 
+/// Labels each voxel of the `config` box with its nearest seed for the Euclidean metric.
+///
+/// The label volume stays on the host and each step goes through it by slabs of at most
+/// `CHUNK_VOXELS` voxels, following `slab_chunks`. The GPU only gets the windows a slab reads,
+/// the slabs `k` layers below and above it, and writes the updated slab to a fourth window.
+pub async fn run_3d(
+    points: &[(f64, f64, f64)],
+    config: (f64, f64, f64),
+    reso: (usize, usize, usize),
+) -> Result<Vec<u32>, &'static str> {
+    let (reso_x, reso_y, reso_z) = reso;
+    let layer = reso_x * reso_y;
+    let slab_layers = (CHUNK_VOXELS / layer).clamp(1, reso_z);
+    let slab_size = layer * slab_layers * std::mem::size_of::<u32>();
+    let context = WgpuContext::new(
+        wgpu::include_wgsl!("shader3d.wgsl"),
+        4 * slab_size,
+        slab_size,
+        points.len() * std::mem::size_of::<(u32, u32, u32)>(),
+        std::mem::size_of::<f32>(),
+    )
+    .await?;
+
+    let snap =
+        |v: f64, reso: usize, size: f64| ((v * reso as f64 / size).min(reso as f64 - 1.0)) as u32;
+    let normal_points: Vec<(u32, u32, u32)> = points
+        .iter()
+        .map(|&(x, y, z)| {
+            (
+                snap(x, reso_x, config.0),
+                snap(y, reso_y, config.1),
+                snap(z, reso_z, config.2),
+            )
+        })
+        .collect();
+
+    let mut local_buffer = vec![0; layer * reso_z];
+    for (i, point) in normal_points.iter().enumerate() {
+        local_buffer[point.0 as usize + (point.1 as usize + point.2 as usize * reso_y) * reso_x] =
+            i as u32 + 1;
+    }
+    let normal_points: Vec<u32> = normal_points
+        .iter()
+        .flat_map(|&(x, y, z)| [x, y, z])
+        .collect();
+    context.queue.write_buffer(
+        &context.normal_points,
+        0,
+        bytemuck::cast_slice(&normal_points),
+    );
+
+    log::info!("Starting 3D JFA iterations...");
+
+    let mut steps = vec![1]; // 1+JFA for more precision
+    let mut k = (reso_x.max(reso_y).max(reso_z) / 2).max(1);
+    while k >= 1 {
+        steps.push(k);
+        k /= 2;
+    }
+
+    // Old labels of the last slab, read by the slab `k` layers above it
+    let mut below = vec![0; layer * slab_layers];
+    let mut updated = vec![0; layer * slab_layers];
+    for k in steps {
+        context
+            .queue
+            .write_buffer(&context.step_buffer, 0, bytemuck::cast_slice(&[k as u32]));
+        for (z0, layers) in slab_chunks(reso_z, k, slab_layers) {
+            let chunk = z0 * layer..(z0 + layers) * layer;
+            let len = chunk.len();
+            let above = (z0 + k).min(reso_z) * layer..(z0 + k + layers).min(reso_z) * layer;
+            // Windows are one after the other: below, the slab, above, then the updated slab
+            let window_size = (len * std::mem::size_of::<u32>()) as u64;
+            if z0 >= k {
+                context.queue.write_buffer(
+                    &context.storage_buffer,
+                    0,
+                    bytemuck::cast_slice(&below[..len]),
+                );
+            }
+            context.queue.write_buffer(
+                &context.storage_buffer,
+                window_size,
+                bytemuck::cast_slice(&local_buffer[chunk.clone()]),
+            );
+            if !above.is_empty() {
+                context.queue.write_buffer(
+                    &context.storage_buffer,
+                    2 * window_size,
+                    bytemuck::cast_slice(&local_buffer[above]),
+                );
+            }
+
+            // Grid uniform: voxel counts, voxel size in domain units, then the layers of the slab
+            let grid = [
+                reso_x as u32,
+                reso_y as u32,
+                reso_z as u32,
+                ((config.0 / reso_x as f64) as f32).to_bits(),
+                ((config.1 / reso_y as f64) as f32).to_bits(),
+                ((config.2 / reso_z as f64) as f32).to_bits(),
+                z0 as u32,
+                layers as u32,
+            ];
+            context
+                .queue
+                .write_buffer(&context.grid_buffer, 0, bytemuck::cast_slice(&grid));
+
+            let mut command_encoder = context
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            {
+                let mut compute_pass =
+                    command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                        label: None,
+                        timestamp_writes: None,
+                    });
+                compute_pass.set_pipeline(&context.pipeline);
+                compute_pass.set_bind_group(0, &context.bind_group, &[]);
+                compute_pass.dispatch_workgroups(
+                    reso_x.div_ceil(WORKGROUP_SIZE_3D) as u32,
+                    reso_y.div_ceil(WORKGROUP_SIZE_3D) as u32,
+                    layers.div_ceil(WORKGROUP_SIZE_3D) as u32,
+                );
+            }
+            context.queue.submit(Some(command_encoder.finish()));
+
+            get_data(
+                &mut updated[..len],
+                3 * window_size,
+                &context.storage_buffer,
+                &context.output_staging_buffer,
+                &context.device,
+                &context.queue,
+            )
+            .await;
+            below[..len].copy_from_slice(&local_buffer[chunk.clone()]);
+            local_buffer[chunk].copy_from_slice(&updated[..len]);
+        }
+    }
+
+    log::info!("done!");
+
+    Ok(local_buffer)
+}
+
+pub fn main_3d(
+    points: &[(f64, f64, f64)],
+    config: (f64, f64, f64),
+    reso: (usize, usize, usize),
+) -> Result<Vec<u32>, &'static str> {
+    if reso.0 == 0 || reso.1 == 0 || reso.2 == 0 {
+        return Err("JFA resolution must be greater than 0");
+    }
+    if points.is_empty() {
+        return Err("There are no points to label voxels with");
+    }
+    pollster::block_on(run_3d(points, config, reso))
+}
+
 struct WgpuContext {
     device: wgpu::Device,
     queue: wgpu::Queue,
@@ -146,22 +377,44 @@ struct WgpuContext {
     storage_buffer: wgpu::Buffer,
     output_staging_buffer: wgpu::Buffer,
     step_buffer: wgpu::Buffer,
+    grid_buffer: wgpu::Buffer,
+    metric_buffer: wgpu::Buffer,
     normal_points: wgpu::Buffer,
+    weights: wgpu::Buffer,
 }
 
 impl WgpuContext {
-    async fn new(buffer_size: usize, points_size: usize) -> WgpuContext {
+    /// Sets up `shader` with a label buffer of `buffer_size` bytes, read back through a staging
+    /// buffer of `staging_size` bytes.
+    async fn new(
+        shader: wgpu::ShaderModuleDescriptor<'_>,
+        buffer_size: usize,
+        staging_size: usize,
+        points_size: usize,
+        weights_size: usize,
+    ) -> Result<WgpuContext, &'static str> {
         let instance = wgpu::Instance::default();
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions::default())
             .await
             .unwrap();
+        let limits = adapter.limits();
+        if buffer_size as u64 > limits.max_buffer_size
+            || buffer_size as u64 > limits.max_storage_buffer_binding_size as u64
+        {
+            return Err("The label grid is larger than the GPU buffers, lower the resolution");
+        }
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
                     required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::downlevel_defaults(),
+                    // Large label grids need the largest buffers the adapter allows
+                    required_limits: wgpu::Limits {
+                        max_buffer_size: limits.max_buffer_size,
+                        max_storage_buffer_binding_size: limits.max_storage_buffer_binding_size,
+                        ..wgpu::Limits::downlevel_defaults()
+                    },
                     memory_hints: wgpu::MemoryHints::Performance,
                 },
                 None,
@@ -169,7 +422,7 @@ impl WgpuContext {
             .await
             .unwrap();
 
-        let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
+        let shader = device.create_shader_module(shader);
 
         let storage_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
@@ -182,7 +435,7 @@ impl WgpuContext {
 
         let output_staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
-            size: buffer_size as wgpu::BufferAddress,
+            size: staging_size as wgpu::BufferAddress,
             usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
             mapped_at_creation: false,
         });
@@ -194,6 +447,20 @@ impl WgpuContext {
             mapped_at_creation: false, //TODO: usage ?
         });
 
+        let grid_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 8 * std::mem::size_of::<u32>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let metric_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 8 * std::mem::size_of::<u32>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
         let normal_points = device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
             size: points_size as wgpu::BufferAddress,
@@ -201,6 +468,13 @@ impl WgpuContext {
             mapped_at_creation: false,
         });
 
+        let weights = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: weights_size as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[
@@ -234,6 +508,36 @@ impl WgpuContext {
                     },
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
         });
 
@@ -253,6 +557,18 @@ impl WgpuContext {
                     binding: 2,
                     resource: normal_points.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: grid_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: metric_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: weights.as_entire_binding(),
+                },
             ],
         });
 
@@ -270,7 +586,7 @@ impl WgpuContext {
             cache: None,
         });
 
-        WgpuContext {
+        Ok(WgpuContext {
             device,
             queue,
             pipeline,
@@ -278,8 +594,11 @@ impl WgpuContext {
             storage_buffer,
             output_staging_buffer,
             step_buffer,
+            grid_buffer,
+            metric_buffer,
             normal_points,
-        }
+            weights,
+        })
     }
 }
 
@@ -1,13 +1,78 @@
+struct Grid {
+    width: u32,
+    height: u32,
+    // Size of a pixel in domain units
+    pixel_width: f32,
+    pixel_height: f32,
+    // 1 when opposite edges of the grid are neighbors
+    periodic: u32,
+}
+
+// Mirrors `Metric::to_uniform`
+struct Metric {
+    // 0: Euclidean, 1: Manhattan, 2: Chebyshev, 3: Lp, 4: anisotropic
+    kind: u32,
+    p: f32,
+    // Coefficients of the symmetric metric tensor
+    m11: f32,
+    m12: f32,
+    m22: f32,
+}
+
 @group(0) @binding(0) var<storage, read_write> pixel_grid: array<u32>;
 @group(0) @binding(1) var<uniform> step: u32;
 @group(0) @binding(2) var<storage, read> normal_points: array<u32>;
+@group(0) @binding(3) var<uniform> grid: Grid;
+@group(0) @binding(4) var<uniform> params: Metric;
+// Power diagram weights, one per seed
+@group(0) @binding(5) var<storage, read> weights: array<f32>;
 
-const RESO: u32 = 512;
+// Same distances as `Metric::distance`
+fn metric(x1: u32, y1: u32, x2: u32, y2: u32) -> f32 {
+    var px = f32(x1) - f32(x2);
+    var py = f32(y1) - f32(y2);
+    if grid.periodic == 1u {
+        // Nearest image of the seed
+        px -= f32(grid.width) * round(px / f32(grid.width));
+        py -= f32(grid.height) * round(py / f32(grid.height));
+    }
+    let dx = px * grid.pixel_width;
+    let dy = py * grid.pixel_height;
+
+    switch params.kind {
+        case 1u: {
+            return abs(dx) + abs(dy);
+        }
+        case 2u: {
+            return max(abs(dx), abs(dy));
+        }
+        case 3u: {
+            let sum = lp_term(dx) + lp_term(dy);
+            return select(pow(sum, 1.0 / params.p), 0.0, sum == 0.0);
+        }
+        case 4u: {
+            return sqrt(params.m11 * dx * dx + 2.0 * params.m12 * dx * dy + params.m22 * dy * dy);
+        }
+        default: {
+            return sqrt(dx * dx + dy * dy);
+        }
+    }
+}
+
+// Power distance `d² - w` to the seed with `color`
+fn power_distance(x: u32, y: u32, color: u32) -> f32 {
+    let d = metric(x, y, normal_points[(color - 1) * 2], normal_points[(color - 1) * 2 + 1]);
+    return d * d - weights[color - 1];
+}
+
+// Remainder of `a / n` in `0..n`, `%` keeps the sign of `a`
+fn modulo(a: i32, n: i32) -> i32 {
+    return ((a % n) + n) % n;
+}
 
-fn metric(x1: u32, y1: u32, x2: u32, y2: u32) -> u32 {
-    let dx = (x1 - x2) * (x1 - x2);
-    let dy = (y1 - y2) * (y1 - y2);
-    return dx + dy;
+// `pow` is undefined for a zero base
+fn lp_term(d: f32) -> f32 {
+    return select(pow(abs(d), params.p), 0.0, d == 0.0);
 }
 
 @compute @workgroup_size(16, 16)
@@ -15,11 +80,11 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let x = global_id.x;
     let y = global_id.y;
 
-    if (x >= RESO || y >= RESO) {
+    if (x >= grid.width || y >= grid.height) {
         return;
     }
 
-    let index: u32 = x + y * RESO;
+    let index: u32 = x + y * grid.width;
     var current_color = pixel_grid[index];
     let initial_position = index;
 
@@ -27,14 +92,17 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
         for (var dy = -1; dy <= 1; dy = dy + 1) {
 
             //TODO: type checking with if to stay with u32?
-            let new_x = u32(i32(x) + dx * i32(step));
-            let new_y = u32(i32(y) + dy * i32(step));
+            var new_x = u32(i32(x) + dx * i32(step));
+            var new_y = u32(i32(y) + dy * i32(step));
 
-            if !(new_x >= 0 && new_x < RESO && new_y >= 0 && new_y < RESO) {
+            if grid.periodic == 1u {
+                new_x = u32(modulo(i32(x) + dx * i32(step), i32(grid.width)));
+                new_y = u32(modulo(i32(y) + dy * i32(step), i32(grid.height)));
+            } else if !(new_x >= 0 && new_x < grid.width && new_y >= 0 && new_y < grid.height) {
                 continue;
             }
 
-            let new_position: u32 = (new_x) + (new_y) * RESO;
+            let new_position: u32 = (new_x) + (new_y) * grid.width;
             let found_color = pixel_grid[new_position];
             current_color = pixel_grid[initial_position];
 
@@ -48,15 +116,8 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
             }
 
             // Assign the closest color to the current pixel
-            let point1_x = normal_points[(current_color - 1)*2];
-            let point2_x = normal_points[(found_color - 1)*2];
-            let point1_y = normal_points[(current_color - 1)*2 + 1];
-            let point2_y = normal_points[(found_color - 1)*2 + 1];
-
-            let dist1 = f32(((x  - point1_x) * (x  - point1_x ) +
-                             (y  - point1_y ) * (y  - point1_y )));
-            let dist2 = f32(((x  - point2_x ) * (x  - point2_x ) +
-                             (y  - point2_y ) * (y  - point2_y )));
+            let dist1 = power_distance(x, y, current_color);
+            let dist2 = power_distance(x, y, found_color);
 
             if dist2 < dist1 {
                 pixel_grid[initial_position] = found_color;
@@ -0,0 +1,73 @@
+struct Grid {
+    width: u32,
+    height: u32,
+    depth: u32,
+    // Size of a voxel in domain units
+    voxel_width: f32,
+    voxel_height: f32,
+    voxel_depth: f32,
+    // Layers of the slab being dispatched
+    z_offset: u32,
+    layers: u32,
+}
+
+// Four windows of `layers` layers: the slab `step` layers below, the slab, the slab `step` layers
+// above, then the updated slab
+@group(0) @binding(0) var<storage, read_write> voxel_grid: array<u32>;
+@group(0) @binding(1) var<uniform> step: u32;
+@group(0) @binding(2) var<storage, read> normal_points: array<u32>;
+@group(0) @binding(3) var<uniform> grid: Grid;
+
+// Squared Euclidean distance from the voxel to the seed with `color`
+fn distance(voxel: vec3<u32>, color: u32) -> f32 {
+    let seed = vec3<f32>(
+        f32(normal_points[(color - 1) * 3]),
+        f32(normal_points[(color - 1) * 3 + 1]),
+        f32(normal_points[(color - 1) * 3 + 2]),
+    );
+    let d = (vec3<f32>(voxel) - seed) * vec3<f32>(grid.voxel_width, grid.voxel_height, grid.voxel_depth);
+    return dot(d, d);
+}
+
+// Index of a voxel of the slab in `window`, or of the voxel `step` layers below or above it
+fn index(window: u32, voxel: vec3<u32>) -> u32 {
+    let slab_size = grid.layers * grid.height * grid.width;
+    return window * slab_size + voxel.x + (voxel.y + (voxel.z - grid.z_offset) * grid.height) * grid.width;
+}
+
+@compute @workgroup_size(4, 4, 4)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let voxel = vec3<u32>(global_id.x, global_id.y, global_id.z + grid.z_offset);
+
+    if (voxel.x >= grid.width || voxel.y >= grid.height || global_id.z >= grid.layers) {
+        return;
+    }
+
+    let size = vec3<i32>(i32(grid.width), i32(grid.height), i32(grid.depth));
+    var current_color = voxel_grid[index(1u, voxel)];
+
+    // Check the 26-neighborhood and keep the closest seed
+    for (var dz = -1; dz <= 1; dz = dz + 1) {
+        for (var dy = -1; dy <= 1; dy = dy + 1) {
+            for (var dx = -1; dx <= 1; dx = dx + 1) {
+                let neighbor = vec3<i32>(voxel) + vec3<i32>(dx, dy, dz) * i32(step);
+                if (dx == 0 && dy == 0 && dz == 0)
+                    || any(neighbor < vec3<i32>(0)) || any(neighbor >= size) {
+                    continue;
+                }
+
+                // The neighbor is in the same layer of the window below or above
+                let found_color = voxel_grid[index(u32(dz + 1), vec3<u32>(u32(neighbor.x), u32(neighbor.y), voxel.z))];
+                if found_color == 0 || current_color == found_color {
+                    continue;
+                }
+
+                if current_color == 0 || distance(voxel, found_color) < distance(voxel, current_color) {
+                    current_color = found_color;
+                }
+            }
+        }
+    }
+
+    voxel_grid[index(3u, voxel)] = current_color;
+}
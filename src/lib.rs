@@ -1,60 +1,707 @@
+pub mod accuracy;
+pub mod boundary;
+pub mod cells;
 pub mod cli;
+pub mod cmap;
+pub mod delaunay;
+pub mod distance;
+pub mod domain;
+pub mod fem;
+pub mod io;
 pub mod jfa_cpu;
 pub mod jfa_wgpu;
+pub mod lloyd;
+pub mod metric;
 mod mode1;
 mod mode2;
 mod mode3;
+mod mode4;
+pub mod periodic;
 mod plot;
+pub mod raster;
+pub mod render;
+pub mod sizing;
+pub mod voronoi;
+pub mod vtk;
 
-use std::fs::File;
-use std::io::Write;
+/// Generates points in the box, keeping those inside the `--domain` polygon when it is set.
+pub fn generate_points<R: rand::Rng>(
+    cli: &cli::Cli,
+    rng: &mut R,
+) -> Result<Vec<(f64, f64)>, &'static str> {
+    let domain = cli.domain()?;
+    if domain.is_some() && cli.periodic {
+        return Err("Polygonal domains can't be periodic");
+    }
+    if cli.boundary && cli.periodic {
+        return Err("Boundary points can't be placed in a periodic box");
+    }
 
-pub fn generate_points(cli: &cli::Cli) -> Result<Vec<(f64, f64)>, &'static str> {
-    match cli.mode {
+    if cli.boundary {
+        return match cli.mode {
+            cli::Mode::PoissonDisk => Ok(boundary::generate_points(
+                cli.d,
+                cli.x,
+                cli.y,
+                domain.as_ref(),
+                rng,
+            )),
+            _ => Err("Boundary points are only available for Poisson disk sampling"),
+        };
+    }
+
+    let mut points = match cli.mode {
         cli::Mode::GridWithN => Ok(mode1::generate_points(
             cli.n,
             cli.x as usize,
             cli.y as usize,
         )),
         cli::Mode::GridWithD => Ok(mode2::generate_points(cli.d, cli.x, cli.y)),
-        cli::Mode::PoissonDisk => Ok(mode3::generate_points(cli.d, cli.x, cli.y)),
+        cli::Mode::PoissonDisk => Ok(mode3::generate_points(
+            cli.d,
+            cli.x,
+            cli.y,
+            cli.periodic,
+            domain.as_ref(),
+            rng,
+        )),
+        cli::Mode::ParallelPoissonDisk if cli.periodic => {
+            Err("Parallel Poisson disk sampling isn't available for periodic boxes")
+        }
+        cli::Mode::ParallelPoissonDisk => Ok(mode4::generate_points(cli.d, cli.x, cli.y, rng)),
+        cli::Mode::Import => Err("Points are read with --import in import mode"),
+        cli::Mode::VariablePoissonDisk => {
+            let range = cli.distance_range();
+            let sizing = match (&cli.sizing, &cli.sizing_image) {
+                (Some(expr), _) => sizing::expression(expr)?,
+                (None, Some(path)) => sizing::image(path, (cli.x, cli.y), range)?,
+                (None, None) => return Err("Variable density sampling needs a sizing field"),
+            };
+            sizing::generate_points(
+                sizing,
+                range,
+                cli.x,
+                cli.y,
+                cli.periodic,
+                domain.as_ref(),
+                rng,
+            )
+        }
+    }?;
+
+    // Poisson disk sampling already rejects points outside the domain, grids don't
+    if let Some(domain) = domain {
+        points.retain(|&point| domain.contains(point));
+    }
+    Ok(points)
+}
+
+/// Generates points in the `x * y * z` box.
+pub fn generate_points_3d<R: rand::Rng>(
+    cli: &cli::Cli,
+    rng: &mut R,
+) -> Result<Vec<(f64, f64, f64)>, &'static str> {
+    let z = cli.z.ok_or("The depth of the box isn't set")?;
+    if cli.periodic || cli.domain.is_some() || cli.boundary {
+        return Err("Periodic boxes, domains and boundary points are only available in 2D");
+    }
+
+    match cli.mode {
+        cli::Mode::GridWithN => Ok(mode1::generate_points_3d(cli.n, cli.x, cli.y, z)),
+        cli::Mode::GridWithD => Ok(mode2::generate_points_3d(cli.d, cli.x, cli.y, z)),
+        cli::Mode::PoissonDisk => Ok(mode3::generate_points_3d(cli.d, cli.x, cli.y, z, rng)),
+        _ => Err("This mode is only available in 2D"),
+    }
+}
+
+/// Reads the points of the `--import` file, checking they lie in the box and in the `--domain`
+/// polygon when it is set.
+pub fn import_points(cli: &cli::Cli) -> Result<io::WeightedPoints, &'static str> {
+    let path = cli
+        .import
+        .as_ref()
+        .ok_or("Import mode needs a points file, given with --import")?;
+    let (points, weights) = io::read_points(path)?;
+
+    if points
+        .iter()
+        .any(|&(x, y)| !(0.0..=cli.x).contains(&x) || !(0.0..=cli.y).contains(&y))
+    {
+        return Err("Imported points must lie in the box");
+    }
+    if let Some(domain) = cli.domain()? {
+        if !points.iter().all(|&point| domain.contains(point)) {
+            return Err("Imported points must lie in the domain");
+        }
+    }
+    Ok((points, weights))
+}
+
+/// Returns the imported points with their weights, or generated points without weights.
+pub fn load_points<R: rand::Rng>(
+    cli: &cli::Cli,
+    rng: &mut R,
+) -> Result<io::WeightedPoints, &'static str> {
+    if cli.mode == cli::Mode::Import || cli.import.is_some() {
+        import_points(cli)
+    } else {
+        Ok((generate_points(cli, rng)?, None))
+    }
+}
+
+/// Returns the points of the `--import` file, checking they lie in the box, or generated points.
+pub fn load_points_3d<R: rand::Rng>(
+    cli: &cli::Cli,
+    rng: &mut R,
+) -> Result<Vec<(f64, f64, f64)>, &'static str> {
+    if cli.mode != cli::Mode::Import && cli.import.is_none() {
+        return generate_points_3d(cli, rng);
+    }
+
+    let z = cli.z.ok_or("The depth of the box isn't set")?;
+    let path = cli
+        .import
+        .as_ref()
+        .ok_or("Import mode needs a points file, given with --import")?;
+    let points = io::read_points_3d(path)?;
+    let inside = |&(px, py, pz): &(f64, f64, f64)| {
+        (0.0..=cli.x).contains(&px) && (0.0..=cli.y).contains(&py) && (0.0..=z).contains(&pz)
+    };
+    if !points.iter().all(inside) {
+        return Err("Imported points must lie in the box");
     }
+    Ok(points)
 }
 
-pub fn generate_cells(points: &[(f64, f64)], cli: &cli::Cli) -> Result<Vec<usize>, &'static str> {
+/// Labels each pixel with its nearest seed, or with the seed of its power cell when `weights` are
+/// given, printing the backend used and how long it took.
+pub fn generate_cells(
+    points: &[(f64, f64)],
+    weights: Option<&[f64]>,
+    cli: &cli::Cli,
+) -> Result<Vec<usize>, &'static str> {
+    if cli.jfa_mode == cli::JfaMode::None {
+        return Ok(vec![]);
+    }
+    let reso = cli.resolution();
+    println!(
+        "Generating {} with resolution {}*{}...",
+        cli.jfa_mode.describe(),
+        reso.0,
+        reso.1
+    );
+
+    let now = std::time::Instant::now();
+    let pixels = compute_cells(points, weights, cli)?;
+    println!("{:.2?}", now.elapsed());
+    Ok(pixels)
+}
+
+/// Runs the backend picked by `--jfa` without printing anything, for the Lloyd iterations.
+fn compute_cells(
+    points: &[(f64, f64)],
+    weights: Option<&[f64]>,
+    cli: &cli::Cli,
+) -> Result<Vec<usize>, &'static str> {
+    let metric = cli.metric()?;
+    let reso = cli.resolution();
     match cli.jfa_mode {
         cli::JfaMode::None => Ok(vec![]),
         cli::JfaMode::Gpu => {
-            println!("Generating cells using GPU with resolution {}...", cli.res);
-            jfa_wgpu::main(points, (cli.x, cli.y))
+            jfa_wgpu::main(points, weights, (cli.x, cli.y), reso, metric, cli.periodic)
         }
         cli::JfaMode::Cpu => {
-            println!("Generating cells using CPU with resolution {}...", cli.res);
-            jfa_cpu::jfa(points, (cli.x, cli.y))
+            jfa_cpu::jfa(points, weights, (cli.x, cli.y), reso, metric, cli.periodic)
+        }
+        cli::JfaMode::Exact if cli.periodic => {
+            Err("Exact cells are only available for non-periodic boxes")
+        }
+        cli::JfaMode::Exact if weights.is_some() => {
+            Err("Exact cells are only available for unweighted points")
+        }
+        cli::JfaMode::Exact if metric != metric::Metric::Euclidean => {
+            Err("Exact cells are only available for the Euclidean metric")
+        }
+        cli::JfaMode::Exact => {
+            let cells = voronoi::voronoi(points, (cli.x, cli.y))?;
+            Ok(voronoi::rasterize(&cells, reso, (cli.x, cli.y)))
+        }
+        cli::JfaMode::CpuParallel => {
+            jfa_cpu::jfa_parallel(points, weights, (cli.x, cli.y), reso, metric, cli.periodic)
         }
     }
 }
 
-pub fn handle_output(cli: &cli::Cli, points: &Vec<(f64, f64)>, pixels: Option<&Vec<usize>>) {
-    // Export points to a CSV file if specified
+/// Labels each voxel of the `x * y * z` box with its nearest seed.
+pub fn generate_cells_3d(
+    points: &[(f64, f64, f64)],
+    cli: &cli::Cli,
+) -> Result<Vec<u32>, &'static str> {
+    let z = cli.z.ok_or("The depth of the box isn't set")?;
+    if cli.metric()? != metric::Metric::Euclidean {
+        return Err("3D cells are only available for the Euclidean metric");
+    }
+    let reso = cli.resolution_3d();
+    let backend = match cli.jfa_mode {
+        cli::JfaMode::None => return Ok(vec![]),
+        cli::JfaMode::Exact => return Err("Exact cells are only available in 2D"),
+        cli::JfaMode::Gpu => jfa_wgpu::main_3d,
+        cli::JfaMode::Cpu => jfa_cpu::jfa_3d,
+        cli::JfaMode::CpuParallel => jfa_cpu::jfa_3d_parallel,
+    };
+    println!(
+        "Generating {} with resolution {}*{}*{}...",
+        cli.jfa_mode.describe(),
+        reso.0,
+        reso.1,
+        reso.2
+    );
+    let now = std::time::Instant::now();
+    let voxels = backend(points, (cli.x, cli.y, z), reso)?;
+    println!("{:.2?}", now.elapsed());
+    Ok(voxels)
+}
+
+/// Returns the label grid with the pixels outside the `--domain` polygon labeled 0, when it is
+/// set.
+pub fn mask_cells<'a>(
+    pixels: &'a [usize],
+    cli: &cli::Cli,
+) -> Result<std::borrow::Cow<'a, [usize]>, &'static str> {
+    match cli.domain()? {
+        Some(domain) => {
+            let mut masked = pixels.to_vec();
+            domain.mask(&mut masked, cli.resolution(), (cli.x, cli.y));
+            Ok(masked.into())
+        }
+        None => Ok(pixels.into()),
+    }
+}
+
+/// Moves the points to the centroids of their JFA cells for up to `--lloyd` iterations, the
+/// cells being restricted to the `--domain` polygon when it is set.
+pub fn relax_points(
+    points: &[(f64, f64)],
+    weights: Option<&[f64]>,
+    cli: &cli::Cli,
+) -> Result<lloyd::Relaxation, &'static str> {
+    if cli.jfa_mode == cli::JfaMode::None {
+        return Err("Lloyd relaxation needs a JFA mode to compute the cells");
+    }
+    let domain = cli.domain()?;
+    lloyd::lloyd(
+        points,
+        (cli.x, cli.y),
+        cli.resolution(),
+        cli.lloyd,
+        cli.lloyd_tolerance,
+        cli.periodic,
+        |points| {
+            let mut pixels = compute_cells(points, weights, cli)?;
+            if let Some(ref domain) = domain {
+                domain.mask(&mut pixels, cli.resolution(), (cli.x, cli.y));
+            }
+            Ok(pixels)
+        },
+    )
+}
+
+/// Prints how many pixels of the label grid aren't labeled with their nearest seed.
+pub fn report_accuracy(
+    points: &[(f64, f64)],
+    weights: Option<&[f64]>,
+    pixels: &[usize],
+    cli: &cli::Cli,
+) {
+    if cli.jfa_mode == cli::JfaMode::None {
+        return;
+    }
+    if weights.is_some() || cli.periodic {
+        println!("JFA accuracy is only measured for unweighted points in non-periodic boxes");
+        return;
+    }
+    let reso = cli.resolution();
+    let report = cli
+        .metric()
+        .and_then(|metric| accuracy::accuracy(pixels, points, (cli.x, cli.y), reso, metric));
+    match report {
+        Ok(report) => println!(
+            "JFA accuracy ({:?}, resolution {}*{}): {}",
+            cli.jfa_mode, reso.0, reso.1, report
+        ),
+        Err(err) => println!("Problem measuring JFA accuracy: {err}"),
+    }
+}
+
+/// Returns the cells as polygons: computed exactly in `exact` mode, otherwise extracted from the
+/// label grid returned by `generate_cells`, before masking.
+fn generate_polygons(
+    points: &[(f64, f64)],
+    pixels: &[usize],
+    cli: &cli::Cli,
+) -> Result<cells::VoronoiCells, &'static str> {
+    match cli.jfa_mode {
+        cli::JfaMode::None => Ok(cells::VoronoiCells::default()),
+        cli::JfaMode::Exact => voronoi::voronoi(points, (cli.x, cli.y)),
+        _ => cells::extract_cells(
+            pixels,
+            cli.resolution(),
+            (cli.x, cli.y),
+            points.len(),
+            cli.periodic,
+        ),
+    }
+}
+
+/// Returns the pieces of each cell inside the `--domain` polygon, or the whole cells when it isn't
+/// set.
+fn generate_pieces(
+    points: &[(f64, f64)],
+    pixels: &[usize],
+    cli: &cli::Cli,
+) -> Result<Vec<Vec<domain::Polygon>>, &'static str> {
+    let cells = generate_polygons(points, pixels, cli)?;
+    match cli.domain()? {
+        Some(domain) => Ok(domain::clip_cells(&cells, &domain)),
+        None => Ok((0..cells.cells.len())
+            .map(|i| {
+                domain::Polygon::new(cells.polygon(i), Vec::new())
+                    .into_iter()
+                    .collect()
+            })
+            .collect()),
+    }
+}
+
+/// Builds the `--mesh-type` mesh, Voronoi cells being extracted from the label grid before
+/// masking and clipped to the `--domain` polygon when it is set.
+pub fn generate_mesh(
+    points: &[(f64, f64)],
+    pixels: Option<&[usize]>,
+    cli: &cli::Cli,
+) -> Result<vtk::Mesh, &'static str> {
+    match cli.mesh_type {
+        cli::MeshType::Points => Ok(vtk::Mesh::from_points(points)),
+        cli::MeshType::Delaunay => Ok(vtk::Mesh::from_triangulation(&delaunay::triangulate(
+            points,
+            (cli.x, cli.y),
+        )?)),
+        cli::MeshType::Voronoi => {
+            if cli.jfa_mode == cli::JfaMode::None {
+                return Err("Voronoi cells need a JFA mode");
+            }
+            let pixels = pixels.ok_or("Voronoi cells need a label grid")?;
+            let cells = generate_polygons(points, pixels, cli)?;
+            match cli.domain()? {
+                Some(domain) => Ok(vtk::Mesh::from_clipped(&domain::clip_cells(
+                    &cells, &domain,
+                ))),
+                None => Ok(vtk::Mesh::from_voronoi(&cells)),
+            }
+        }
+    }
+}
+
+/// Writes a plot rendered without a browser to `output`, reporting how it went.
+fn save_plot<F>(output: Option<&std::path::Path>, write: F)
+where
+    F: FnOnce(&std::path::Path) -> Result<(), &'static str>,
+{
+    let Some(path) = output else {
+        return;
+    };
+    match write(path) {
+        Ok(()) => println!("Plot written to {}", path.display()),
+        Err(err) => println!("Problem plotting: {err}"),
+    }
+}
+
+/// Writes the 3D points to `--export` and their voxel labels to `--labels`, when they are set.
+pub fn handle_output_3d(cli: &cli::Cli, points: &[(f64, f64, f64)], voxels: Option<&[u32]>) {
     if let Some(ref export_path) = cli.export {
-        let mut file = File::create(export_path).expect("Unable to create file");
-        for (x, y) in points {
-            file.write_all(format!("{},{}\n", x, y).as_bytes())
-                .expect("Unable to write data");
+        io::write_points_csv_3d(export_path, points).expect("Unable to write data");
+        println!("Points written to {}", export_path.display());
+    }
+
+    if let (Some(labels_path), Some(voxels)) = (&cli.labels, voxels) {
+        let reso = cli.resolution_3d();
+        let written = match cli.labels_format {
+            cli::LabelFormat::Npy => {
+                raster::write_npy(labels_path, voxels, &[reso.2, reso.1, reso.0])
+            }
+            _ => Err("Voxel labels can only be exported as npy"),
+        };
+        match written {
+            Ok(()) => println!("Labels written to {}", labels_path.display()),
+            Err(err) => println!("Problem exporting labels: {err}"),
         }
+    }
+}
+
+/// Writes the outputs set on the command line. `pixels` is the label grid returned by
+/// `generate_cells`, which is masked here for the raster outputs.
+pub fn handle_output<R: rand::Rng>(
+    cli: &cli::Cli,
+    points: &[(f64, f64)],
+    weights: Option<&[f64]>,
+    pixels: Option<&[usize]>,
+    rng: &mut R,
+) {
+    // Export points to a CSV file if specified
+    if let Some(ref export_path) = cli.export {
+        io::write_points_csv(export_path, points, weights).expect("Unable to write data");
         println!("Points written to {}", export_path.display());
     }
 
+    // Export the Delaunay triangulation of the points if specified
+    if let Some(ref triangulation_path) = cli.triangulation {
+        match delaunay::triangulate(points, (cli.x, cli.y)) {
+            Ok(triangulation) => {
+                triangulation
+                    .write_off(triangulation_path)
+                    .expect("Unable to write triangulation");
+                println!(
+                    "Triangulation with {} triangles written to {}",
+                    triangulation.triangles.len(),
+                    triangulation_path.display()
+                );
+            }
+            Err(err) => println!("Problem triangulating points: {err}"),
+        }
+    }
+
+    // Export a mesh for ParaView or finite element codes if specified
+    if cli.vtk.is_some() || cli.mesh.is_some() {
+        match generate_mesh(points, pixels, cli) {
+            Ok(mesh) => {
+                if let Some(ref vtk_path) = cli.vtk {
+                    mesh.write(vtk_path).expect("Unable to write mesh");
+                    println!(
+                        "Mesh with {} cells written to {}",
+                        mesh.cells.len(),
+                        vtk_path.display()
+                    );
+                }
+                if let Some(ref mesh_path) = cli.mesh {
+                    fem::write(&mesh, mesh_path).expect("Unable to write mesh");
+                    println!(
+                        "Mesh with {} cells written to {}",
+                        mesh.cells.len(),
+                        mesh_path.display()
+                    );
+                }
+            }
+            Err(err) => println!("Problem building mesh: {err}"),
+        }
+    }
+
     if matches!(cli.plot, cli::PlotMode::Points) {
-        println!("Plotting points...");
-        plot::plot_points(points);
+        let output = cli.plot_output.as_deref();
+        match output.map(render::format).transpose() {
+            Ok(Some(render::Format::Png)) => {
+                save_plot(output, |path| {
+                    render::render_points(points, (cli.x, cli.y))
+                        .save(path)
+                        .map_err(|_| "Unable to write the plot")
+                });
+            }
+            Ok(Some(render::Format::Svg)) => {
+                save_plot(output, |path| {
+                    std::fs::write(path, render::draw_points_svg(points, (cli.x, cli.y)))
+                        .map_err(|_| "Unable to write the plot")
+                });
+            }
+            Ok(_) => {
+                println!("Plotting points...");
+                plot::plot_points(points, output);
+                if let Some(path) = output {
+                    println!("Plot written to {}", path.display());
+                }
+            }
+            Err(err) => println!("Problem plotting: {err}"),
+        }
     }
 
-    if let Some(pixels) = pixels {
+    if let Some(cells) = pixels {
+        let pixels = match mask_cells(cells, cli) {
+            Ok(pixels) => pixels,
+            Err(err) => {
+                println!("Problem masking cells: {err}");
+                return;
+            }
+        };
+        let pixels = &pixels[..];
+
+        // Export the label grid if specified
+        if let Some(ref labels_path) = cli.labels {
+            let reso = cli.resolution();
+            let written = match cli.labels_format {
+                cli::LabelFormat::Colored => {
+                    let colors = render::label_colors(points.len(), rng);
+                    raster::write_colored_png(labels_path, pixels, reso, &colors)
+                }
+                cli::LabelFormat::Gray16 => raster::write_gray16_png(labels_path, pixels, reso),
+                cli::LabelFormat::Gray32 => raster::write_gray32_tiff(labels_path, pixels, reso),
+                cli::LabelFormat::Npy => raster::write_npy_2d(labels_path, pixels, reso),
+            };
+            match written {
+                Ok(()) => println!("Labels written to {}", labels_path.display()),
+                Err(err) => println!("Problem exporting labels: {err}"),
+            }
+        }
+
+        // Export the distance transform if specified
+        if let Some(ref distance_path) = cli.distance_field {
+            let field = cli.metric().and_then(|metric| {
+                distance::distance_transform(
+                    pixels,
+                    points,
+                    (cli.x, cli.y),
+                    cli.resolution(),
+                    metric,
+                    true,
+                    cli.periodic,
+                )
+            });
+            match field {
+                Ok(field) => {
+                    field
+                        .write_csv(distance_path)
+                        .expect("Unable to write distances");
+                    println!("Distances written to {}", distance_path.display());
+                }
+                Err(err) => println!("Problem computing distances: {err}"),
+            }
+        }
+
         if matches!(cli.plot, cli::PlotMode::Jfa) {
-            println!("Plotting cells...");
-            plot::plot_heatmap_with_points(pixels, points, (cli.x, cli.y));
+            let output = cli.plot_output.as_deref();
+            match output.map(render::format).transpose() {
+                Ok(Some(render::Format::Png)) => {
+                    let colors = render::label_colors(points.len(), rng);
+                    save_plot(output, |path| {
+                        render::render_cells(
+                            pixels,
+                            cli.resolution(),
+                            points,
+                            (cli.x, cli.y),
+                            &colors,
+                        )?
+                        .save(path)
+                        .map_err(|_| "Unable to write the plot")
+                    });
+                }
+                Ok(Some(render::Format::Svg)) => {
+                    let colors = render::label_colors(points.len(), rng);
+                    save_plot(output, |path| {
+                        let pieces = generate_pieces(points, cells, cli)?;
+                        let svg = render::draw_cells_svg(&pieces, points, (cli.x, cli.y), &colors);
+                        std::fs::write(path, svg).map_err(|_| "Unable to write the plot")
+                    });
+                }
+                Ok(_) => {
+                    println!("Plotting cells...");
+                    plot::plot_heatmap_with_points(
+                        pixels,
+                        cli.resolution(),
+                        points,
+                        (cli.x, cli.y),
+                        rng,
+                        output,
+                    );
+                    if let Some(path) = output {
+                        println!("Plot written to {}", path.display());
+                    }
+                }
+                Err(err) => println!("Problem plotting: {err}"),
+            }
+        }
+
+        if matches!(cli.plot, cli::PlotMode::Distance) {
+            let field = cli.metric().and_then(|metric| {
+                distance::distance_transform(
+                    pixels,
+                    points,
+                    (cli.x, cli.y),
+                    cli.resolution(),
+                    metric,
+                    false,
+                    cli.periodic,
+                )
+            });
+            match field {
+                Ok(field) => {
+                    let output = cli.plot_output.as_deref();
+                    match output.map(render::format).transpose() {
+                        Ok(Some(render::Format::Png)) => {
+                            save_plot(output, |path| {
+                                render::render_distance(&field, points, (cli.x, cli.y))
+                                    .save(path)
+                                    .map_err(|_| "Unable to write the plot")
+                            });
+                        }
+                        Ok(Some(render::Format::Svg)) => println!(
+                            "Problem plotting: distance fields can only be saved as HTML or PNG"
+                        ),
+                        Ok(_) => {
+                            println!("Plotting distances...");
+                            plot::plot_distance_field(&field, points, (cli.x, cli.y), output);
+                            if let Some(path) = output {
+                                println!("Plot written to {}", path.display());
+                            }
+                        }
+                        Err(err) => println!("Problem plotting: {err}"),
+                    }
+                }
+                Err(err) => println!("Problem computing distances: {err}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_domain_cells() {
+        // L-shaped domain, without the top right quarter of the box
+        let path = std::env::temp_dir().join("blue_noise_test_lib_domain.txt");
+        std::fs::write(&path, "0,0\n10,0\n10,5\n5,5\n5,10\n0,10\n").unwrap();
+
+        for jfa_mode in ["cpu-parallel", "exact"] {
+            let cli = cli::Cli::parse_from([
+                "blue_noise",
+                "-r",
+                "256",
+                "-s",
+                "42",
+                "-j",
+                jfa_mode,
+                "--domain",
+                path.to_str().unwrap(),
+            ]);
+            let points = generate_points(&cli, &mut cli.rng()).unwrap();
+            let pixels = generate_cells(&points, None, &cli).unwrap();
+
+            // Cells are clipped to the domain, and still cover it
+            let mesh = generate_mesh(&points, Some(&pixels), &cli).unwrap();
+            let vtk::CellData::Float(ref areas) = mesh.cell_data[0].1 else {
+                panic!("Cell areas are missing");
+            };
+            assert!((areas.iter().sum::<f64>() - 75.0).abs() < 0.1);
+            assert!(mesh
+                .points
+                .iter()
+                .all(|&(x, y)| x < 5.0 + 1e-6 || y < 5.0 + 1e-6));
+
+            let pieces = generate_pieces(&points, &pixels, &cli).unwrap();
+            assert_eq!(pieces.len(), points.len());
+            let area: f64 = pieces.iter().flatten().map(|piece| piece.area()).sum();
+            assert!((area - 75.0).abs() < 0.1);
         }
+
+        std::fs::remove_file(path).unwrap();
     }
 }
@@ -0,0 +1,163 @@
+// Lloyd relaxation towards a centroidal Voronoi tessellation (CVT).
+//
+// Each iteration labels the pixels with their nearest seed, then moves every seed to the centroid
+// of the pixels of its cell.
+
+use crate::periodic::nearest_image;
+
+/// Relaxed points, and the CVT energy of the cells at each iteration.
+#[derive(Debug, Clone, Default)]
+pub struct Relaxation {
+    pub points: Vec<(f64, f64)>,
+    /// Energy of the cells before moving the seeds, one value per iteration
+    pub energies: Vec<f64>,
+}
+
+/// Centroids of the cells of a label grid.
+#[derive(Debug, Clone, Default)]
+pub struct Centroids {
+    /// One centroid per seed, seeds of cells without any pixel are their own centroid
+    pub centroids: Vec<(f64, f64)>,
+    /// Sum over pixels of the squared distance from the pixel center to its seed, times the
+    /// pixel area
+    pub energy: f64,
+}
+
+/// Returns the shortest image of the vector `(dx, dy)` when the box wraps around.
+fn offset((dx, dy): (f64, f64), config: (f64, f64), periodic: bool) -> (f64, f64) {
+    if periodic {
+        nearest_image((dx, dy), config)
+    } else {
+        (dx, dy)
+    }
+}
+
+/// Returns the centroid of each cell of a label grid, and the CVT energy of the cells.
+///
+/// In a `periodic` box, pixels count from the image closest to their seed, and centroids are
+/// wrapped back into the box. Pixels labeled 0, outside the domain, are left out.
+pub fn centroids(
+    pixels: &[usize],
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    reso: (usize, usize),
+    periodic: bool,
+) -> Result<Centroids, &'static str> {
+    let (reso_x, reso_y) = reso;
+    if pixels.len() != reso_x * reso_y {
+        return Err("Label grid size doesn't match the resolution");
+    }
+    let pixel_size = (config.0 / reso_x as f64, config.1 / reso_y as f64);
+
+    let mut sums = vec![(0.0, 0.0, 0usize); points.len()];
+    let mut energy = 0.0;
+    for (p, &label) in pixels.iter().enumerate() {
+        if label == 0 {
+            continue;
+        }
+        if label > points.len() {
+            return Err("Label grid contains pixels without a valid seed");
+        }
+        let x = ((p % reso_x) as f64 + 0.5) * pixel_size.0;
+        let y = ((p / reso_x) as f64 + 0.5) * pixel_size.1;
+        let seed = points[label - 1];
+        let (dx, dy) = offset((x - seed.0, y - seed.1), config, periodic);
+        energy += dx * dx + dy * dy;
+
+        // Sum offsets from the seed rather than positions, cells may cross a periodic edge
+        let sum = &mut sums[label - 1];
+        sum.0 += dx;
+        sum.1 += dy;
+        sum.2 += 1;
+    }
+
+    let centroids = sums
+        .iter()
+        .zip(points)
+        .map(|(&(dx, dy, count), &seed)| {
+            if count == 0 {
+                return seed;
+            }
+            let (x, y) = (seed.0 + dx / count as f64, seed.1 + dy / count as f64);
+            if periodic {
+                (x.rem_euclid(config.0), y.rem_euclid(config.1))
+            } else {
+                (x, y)
+            }
+        })
+        .collect();
+    Ok(Centroids {
+        centroids,
+        energy: energy * pixel_size.0 * pixel_size.1,
+    })
+}
+
+/// Runs up to `iterations` Lloyd iterations, labeling the pixels with `label` at each one.
+///
+/// Stops early once no seed moves by more than `tolerance`. `periodic` must match how `label`
+/// treats the box.
+pub fn lloyd<F>(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    reso: (usize, usize),
+    iterations: usize,
+    tolerance: f64,
+    periodic: bool,
+    mut label: F,
+) -> Result<Relaxation, &'static str>
+where
+    F: FnMut(&[(f64, f64)]) -> Result<Vec<usize>, &'static str>,
+{
+    let mut points = points.to_vec();
+    let mut energies = Vec::with_capacity(iterations);
+
+    for _ in 0..iterations {
+        let pixels = label(&points)?;
+        let Centroids { centroids, energy } = centroids(&pixels, &points, config, reso, periodic)?;
+        energies.push(energy);
+
+        let max_move = points
+            .iter()
+            .zip(&centroids)
+            .map(|(a, b)| {
+                let (dx, dy) = offset((a.0 - b.0, a.1 - b.1), config, periodic);
+                (dx * dx + dy * dy).sqrt()
+            })
+            .fold(0.0, f64::max);
+        points = centroids;
+        if max_move <= tolerance {
+            break;
+        }
+    }
+
+    Ok(Relaxation { points, energies })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jfa_cpu::jfa;
+    use crate::metric::Metric;
+
+    #[test]
+    fn test_lloyd() {
+        let points = vec![(1.0, 1.0), (1.5, 1.2), (2.0, 0.5), (1.2, 2.0), (9.0, 9.0)];
+        let config = (10.0, 10.0);
+        let reso = (64, 64);
+
+        let relaxation = lloyd(&points, config, reso, 30, 1e-3, false, |points| {
+            jfa(points, None, config, reso, Metric::Euclidean, false)
+        })
+        .unwrap();
+
+        assert_eq!(relaxation.points.len(), points.len());
+        assert!(relaxation
+            .points
+            .iter()
+            .all(|p| (0.0..=10.0).contains(&p.0) && (0.0..=10.0).contains(&p.1)));
+        // Clustered seeds spread out, lowering the energy
+        let first = relaxation.energies[0];
+        let last = *relaxation.energies.last().unwrap();
+        assert!(last < 0.5 * first);
+    }
+}
@@ -3,18 +3,52 @@ use blue_noise::*;
 fn main() {
     let cli = cli::parse();
     cli::print_config(&cli);
+    let mut rng = cli.rng();
+
+    if cli.z.is_some() {
+        let points = load_points_3d(&cli, &mut rng).unwrap_or_else(|err| {
+            println!("Problem generating points: {err}");
+            std::process::exit(1);
+        });
+        println!("Generated {} points", points.len());
+        let voxels = generate_cells_3d(&points, &cli).unwrap_or_else(|err| {
+            println!("Problem running JFA: {err}");
+            std::process::exit(1);
+        });
+        if !voxels.is_empty() {
+            println!("Labeled {} voxels", voxels.len());
+        }
+        let voxels = (!voxels.is_empty()).then_some(&voxels[..]);
+        handle_output_3d(&cli, &points, voxels);
+        return;
+    }
 
     // Processing
-    let points = generate_points(&cli).unwrap_or_else(|err| {
+    let (mut points, weights) = load_points(&cli, &mut rng).unwrap_or_else(|err| {
         println!("Problem generating points: {err}");
         std::process::exit(1);
     });
 
-    let pixels = generate_cells(&points, &cli).unwrap_or_else(|err| {
+    if cli.lloyd > 0 {
+        let relaxation = relax_points(&points, weights.as_deref(), &cli).unwrap_or_else(|err| {
+            println!("Problem relaxing points: {err}");
+            std::process::exit(1);
+        });
+        for (i, energy) in relaxation.energies.iter().enumerate() {
+            println!("Lloyd iteration {}: energy {:.6}", i + 1, energy);
+        }
+        points = relaxation.points;
+    }
+
+    let pixels = generate_cells(&points, weights.as_deref(), &cli).unwrap_or_else(|err| {
         println!("Problem running JFA: {err}");
         std::process::exit(1);
     });
 
+    if cli.accuracy {
+        report_accuracy(&points, weights.as_deref(), &pixels, &cli);
+    }
+
     // Output
-    handle_output(&cli, &points, Some(&pixels));
+    handle_output(&cli, &points, weights.as_deref(), Some(&pixels), &mut rng);
 }
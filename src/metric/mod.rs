@@ -0,0 +1,128 @@
+// Distance metrics used to compare seeds when generating cells.
+
+/// Distance between two points, measured on their difference `(dx, dy)` in domain units.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum Metric {
+    #[default]
+    Euclidean,
+    Manhattan,
+    Chebyshev,
+    /// `(|dx|^p + |dy|^p)^(1/p)`, with `p >= 1`
+    Lp(f64),
+    /// `sqrt(d^T M d)` for the symmetric positive definite tensor `M = [[m11, m12], [m12, m22]]`,
+    /// given as `[m11, m12, m22]`
+    Anisotropic([f64; 3]),
+}
+
+impl Metric {
+    /// Builds an `Lp` metric, which is only a metric for `p >= 1`.
+    pub fn lp(p: f64) -> Result<Metric, &'static str> {
+        if p.is_finite() && p >= 1.0 {
+            Ok(Metric::Lp(p))
+        } else {
+            Err("The exponent of an Lp metric must be at least 1")
+        }
+    }
+
+    /// Builds an anisotropic metric from the `[m11, m12, m22]` coefficients of its tensor.
+    pub fn anisotropic(tensor: [f64; 3]) -> Result<Metric, &'static str> {
+        let [m11, m12, m22] = tensor;
+        if m11 > 0.0 && m11 * m22 - m12 * m12 > 0.0 {
+            Ok(Metric::Anisotropic(tensor))
+        } else {
+            Err("The metric tensor must be positive definite")
+        }
+    }
+
+    pub fn distance(&self, dx: f64, dy: f64) -> f64 {
+        match *self {
+            Metric::Euclidean => (dx * dx + dy * dy).sqrt(),
+            Metric::Manhattan => dx.abs() + dy.abs(),
+            Metric::Chebyshev => dx.abs().max(dy.abs()),
+            Metric::Lp(p) => (dx.abs().powf(p) + dy.abs().powf(p)).powf(1.0 / p),
+            Metric::Anisotropic([m11, m12, m22]) => {
+                (m11 * dx * dx + 2.0 * m12 * dx * dy + m22 * dy * dy).sqrt()
+            }
+        }
+    }
+
+    /// Returns a factor `k` such that the distance is never less than `k` times the Euclidean
+    /// distance, to bound nearest seed searches.
+    pub fn euclidean_lower_bound(&self) -> f64 {
+        match *self {
+            Metric::Euclidean | Metric::Manhattan => 1.0,
+            Metric::Chebyshev => std::f64::consts::FRAC_1_SQRT_2,
+            Metric::Lp(p) if p <= 2.0 => 1.0,
+            Metric::Lp(p) => 2.0_f64.powf(1.0 / p - 0.5),
+            Metric::Anisotropic([m11, m12, m22]) => {
+                // Square root of the smallest eigenvalue of the tensor
+                let half_trace = (m11 + m22) / 2.0;
+                let gap = (((m11 - m22) / 2.0).powi(2) + m12 * m12).sqrt();
+                (half_trace - gap).max(0.0).sqrt()
+            }
+        }
+    }
+
+    /// Packs the metric for the `Metric` uniform of the shader: its kind, the Lp exponent and the
+    /// tensor coefficients, padded to 8 words.
+    pub(crate) fn to_uniform(self) -> [u32; 8] {
+        let (kind, p, [m11, m12, m22]) = match self {
+            Metric::Euclidean => (0, 2.0, [1.0, 0.0, 1.0]),
+            Metric::Manhattan => (1, 1.0, [1.0, 0.0, 1.0]),
+            Metric::Chebyshev => (2, f64::INFINITY, [1.0, 0.0, 1.0]),
+            Metric::Lp(p) => (3, p, [1.0, 0.0, 1.0]),
+            Metric::Anisotropic(tensor) => (4, 2.0, tensor),
+        };
+        [
+            kind,
+            (p as f32).to_bits(),
+            (m11 as f32).to_bits(),
+            (m12 as f32).to_bits(),
+            (m22 as f32).to_bits(),
+            0,
+            0,
+            0,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distances() {
+        let (dx, dy) = (3.0, -4.0);
+        assert_eq!(Metric::Euclidean.distance(dx, dy), 5.0);
+        assert_eq!(Metric::Manhattan.distance(dx, dy), 7.0);
+        assert_eq!(Metric::Chebyshev.distance(dx, dy), 4.0);
+        assert!((Metric::lp(2.0).unwrap().distance(dx, dy) - 5.0).abs() < 1e-12);
+        assert!((Metric::lp(1.0).unwrap().distance(dx, dy) - 7.0).abs() < 1e-12);
+        // Stretching x by 2
+        let metric = Metric::anisotropic([4.0, 0.0, 1.0]).unwrap();
+        assert!((metric.distance(dx, dy) - 52.0_f64.sqrt()).abs() < 1e-12);
+
+        assert!(Metric::lp(0.5).is_err());
+        assert!(Metric::anisotropic([1.0, 2.0, 1.0]).is_err());
+    }
+
+    #[test]
+    fn test_euclidean_lower_bound() {
+        let metrics = [
+            Metric::Euclidean,
+            Metric::Manhattan,
+            Metric::Chebyshev,
+            Metric::Lp(1.5),
+            Metric::Lp(5.0),
+            Metric::Anisotropic([2.0, 0.5, 0.3]),
+        ];
+        for metric in metrics {
+            let k = metric.euclidean_lower_bound();
+            for i in 0..64 {
+                let angle = i as f64 * std::f64::consts::PI / 32.0;
+                let (dx, dy) = (angle.cos(), angle.sin());
+                assert!(metric.distance(dx, dy) >= k - 1e-12, "{:?}", metric);
+            }
+        }
+    }
+}
@@ -47,6 +47,52 @@ pub fn generate_points(n: u32, width: usize, height: usize) -> Vec<(f64, f64)> {
     points
 }
 
+/// Returns the `(cols, rows, layers)` splitting `n` whose spacings along the three axes of the
+/// `x * y * z` box are the closest to each other.
+fn best_grid_dimensions_3d(n: usize, x: f64, y: f64, z: f64) -> (usize, usize, usize) {
+    let mut best = (n, 1, 1);
+    let mut best_spread = f64::MAX;
+
+    for r in (1..=n).filter(|r| n % r == 0) {
+        for l in (1..=n / r).filter(|l| (n / r) % l == 0) {
+            let c = n / (r * l);
+            let spacings = [x / c as f64, y / r as f64, z / l as f64];
+            let max = spacings.iter().cloned().fold(f64::MIN, f64::max);
+            let min = spacings.iter().cloned().fold(f64::MAX, f64::min);
+            if max / min < best_spread {
+                best_spread = max / min;
+                best = (c, r, l);
+            }
+        }
+    }
+
+    best
+}
+
+/// 3D equivalent of `generate_points`, filling the `width * height * depth` box.
+pub fn generate_points_3d(n: u32, width: f64, height: f64, depth: f64) -> Vec<(f64, f64, f64)> {
+    let (cols, rows, layers) = best_grid_dimensions_3d(n as usize, width, height, depth);
+
+    let x_spacing = width / (cols + 1) as f64;
+    let y_spacing = height / (rows + 1) as f64;
+    let z_spacing = depth / (layers + 1) as f64;
+
+    let mut points = Vec::with_capacity(n as usize);
+    for l in 0..layers {
+        for r in 0..rows {
+            for c in 0..cols {
+                points.push((
+                    (c + 1) as f64 * x_spacing,
+                    (r + 1) as f64 * y_spacing,
+                    (l + 1) as f64 * z_spacing,
+                ));
+            }
+        }
+    }
+
+    points
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -92,4 +138,14 @@ mod tests {
             assert!((point.1 - expected.1).abs() < f64::EPSILON);
         }
     }
+
+    #[test]
+    fn test_fit_points_in_box() {
+        assert_eq!(best_grid_dimensions_3d(24, 4.0, 3.0, 2.0), (4, 3, 2));
+
+        let points = generate_points_3d(8, 3.0, 3.0, 3.0);
+        assert_eq!(points.len(), 8);
+        assert_eq!(points[0], (1.0, 1.0, 1.0));
+        assert_eq!(points[7], (2.0, 2.0, 2.0));
+    }
 }
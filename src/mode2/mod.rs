@@ -17,6 +17,25 @@ pub fn generate_points(d: f64, width: f64, height: f64) -> Vec<(f64, f64)> {
     points
 }
 
+/// 3D equivalent of `generate_points`, filling the `width * height * depth` box.
+pub fn generate_points_3d(d: f64, width: f64, height: f64, depth: f64) -> Vec<(f64, f64, f64)> {
+    let mut points = Vec::new();
+
+    let num_points_x = (width / d).floor() as usize;
+    let num_points_y = (height / d).floor() as usize;
+    let num_points_z = (depth / d).floor() as usize;
+
+    for i in 0..=num_points_x {
+        for j in 0..=num_points_y {
+            for k in 0..=num_points_z {
+                points.push((i as f64 * d, j as f64 * d, k as f64 * d));
+            }
+        }
+    }
+
+    points
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -66,4 +85,12 @@ mod test {
         assert_eq!(points.len(), 1);
         assert_eq!(points[0], (0.0, 0.0));
     }
+
+    #[test]
+    fn test_generate_grid_points_3d_count() {
+        let points = generate_points_3d(10.0, 100.0, 50.0, 20.0);
+
+        assert_eq!(points.len(), 11 * 6 * 3);
+        assert_eq!(points.last(), Some(&(100.0, 50.0, 20.0)));
+    }
 }
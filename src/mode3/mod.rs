@@ -1,12 +1,31 @@
 // https://a5huynh.github.io/posts/2019/poisson-disk-sampling/
 
 use rand::prelude::*;
-struct PoissonDisk {
-    minimum_distance: f64,
+
+use crate::domain::Polygon;
+use crate::periodic::nearest_image;
+
+/// Number of candidates tried around an active point before retiring it.
+const NUM_SAMPLES: usize = 30;
+
+/// Number of tries to find a first point inside the domain.
+const FIRST_POINT_TRIES: usize = 1000;
+
+struct PoissonDisk<'a, F: Fn((f64, f64)) -> f64> {
+    /// Minimum distance around a point, within `[min_distance, max_distance]`
+    radius: F,
+    max_distance: f64,
     num_samples: usize,
     width: f64,
     height: f64,
-    cell_size: f64,
+    /// Whether the box wraps around, opposite edges being neighbors
+    periodic: bool,
+    /// Region of the box where candidates are accepted
+    domain: Option<&'a Polygon>,
+    /// Distance between the far edges of the box and the candidates clamped inside it
+    edge_margin: f64,
+    /// Width and height of a grid cell
+    cell_size: (f64, f64),
     grid_width: f64,
     grid_height: f64,
     grid: Vec<Option<(f64, f64)>>,
@@ -14,82 +33,168 @@ struct PoissonDisk {
     active: Vec<(f64, f64)>,
 }
 
-impl PoissonDisk {
-    fn new(w: f64, h: f64, r: f64, k: usize) -> Self {
-        let cell_size = r / 2.0_f64.sqrt();
-        let grid_width = (w / cell_size).ceil() + 1.0;
-        let grid_height = (h / cell_size).ceil() + 1.0;
+impl<'a, F: Fn((f64, f64)) -> f64> PoissonDisk<'a, F> {
+    fn new(
+        w: f64,
+        h: f64,
+        radius: F,
+        (min_distance, max_distance): (f64, f64),
+        periodic: bool,
+        domain: Option<&'a Polygon>,
+    ) -> Self {
+        // At most one point per cell, even where points are the closest
+        let largest_cell = min_distance / 2.0_f64.sqrt();
+        let (cell_size, grid_width, grid_height) = if periodic {
+            // Cells shrink to tile the period exactly, so that the grid wraps around like the box
+            let columns = (w / largest_cell).ceil();
+            let rows = (h / largest_cell).ceil();
+            ((w / columns, h / rows), columns, rows)
+        } else {
+            let columns = (w / largest_cell).ceil() + 1.0;
+            let rows = (h / largest_cell).ceil() + 1.0;
+            ((largest_cell, largest_cell), columns, rows)
+        };
 
-        let mut disk = PoissonDisk {
-            minimum_distance: r,
-            num_samples: k,
+        PoissonDisk {
+            radius,
+            max_distance,
+            num_samples: NUM_SAMPLES,
             width: w,
             height: h,
+            periodic,
+            domain,
+            edge_margin: 1.0,
             cell_size,
             grid_width,
             grid_height,
             grid: vec![None; (grid_width * grid_height) as usize],
             samples: Vec::new(),
             active: Vec::new(),
-        };
+        }
+    }
 
-        let mut rng = rand::thread_rng();
-        let point = ((rng.gen::<f64>() * w), (rng.gen::<f64>() * h));
+    /// Starts from a random point of the domain.
+    fn start<R: Rng>(&mut self, rng: &mut R) {
+        let (w, h) = (self.width, self.height);
+        let mut point = ((rng.gen::<f64>() * w), (rng.gen::<f64>() * h));
+        for _ in 1..FIRST_POINT_TRIES {
+            if self.in_domain(point) {
+                break;
+            }
+            point = ((rng.gen::<f64>() * w), (rng.gen::<f64>() * h));
+        }
+        if !self.in_domain(point) {
+            return;
+        }
 
         // Add point to grid & active list.
-        disk.insert_point(point);
-        disk.active.push(point);
+        self.insert_point(point);
+        self.active.push(point);
+    }
+
+    /// Starts from `seeds`, which are kept in the samples. They must be in the box and far
+    /// enough from each other.
+    fn start_from(&mut self, seeds: &[(f64, f64)]) {
+        for &seed in seeds {
+            self.insert_point(seed);
+            self.active.push(seed);
+            self.samples.push(seed);
+        }
+    }
+
+    fn in_domain(&self, point: (f64, f64)) -> bool {
+        self.domain.is_none_or(|domain| domain.contains(point))
+    }
 
-        disk
+    /// Returns the column and row of the cell holding `point`.
+    fn cell_of(&self, point: (f64, f64)) -> (f64, f64) {
+        // Rounding may put points right below the period in the next cell
+        (
+            (point.0 / self.cell_size.0)
+                .floor()
+                .min(self.grid_width - 1.0),
+            (point.1 / self.cell_size.1)
+                .floor()
+                .min(self.grid_height - 1.0),
+        )
     }
 
     fn insert_point(&mut self, point: (f64, f64)) {
         // Calculate the (x, y) coordinate when place inside the grid.
-        let cell_x = (point.0 / self.cell_size).floor();
-        let cell_y = (point.1 / self.cell_size).floor();
+        let (cell_x, cell_y) = self.cell_of(point);
 
         // Calculate the index within our flat array and place the point there.
         let cell_idx = (cell_y * self.grid_width + cell_x) as usize;
         self.grid[cell_idx] = Some(point);
     }
 
-    fn generate_around(&mut self, pt: (f64, f64)) -> (f64, f64) {
+    fn generate_around<R: Rng>(&mut self, pt: (f64, f64), rng: &mut R) -> (f64, f64) {
         // Random angle and radius between r and 2r
-        let mut rng = rand::thread_rng();
         let angle = 2.0 * std::f64::consts::PI * rng.gen::<f64>();
-        let radius = self.minimum_distance * (rng.gen::<f64>() + 1.0);
+        let radius = (self.radius)(pt) * (rng.gen::<f64>() + 1.0);
 
         let new_x = pt.0 + (radius * angle.cos());
         let new_y = pt.1 + (radius * angle.sin());
 
+        if self.periodic {
+            // `rem_euclid` may round up to the period itself
+            let wrap = |v: f64, period: f64| match v.rem_euclid(period) {
+                v if v < period => v,
+                _ => 0.0,
+            };
+            return (wrap(new_x, self.width), wrap(new_y, self.height));
+        }
+
         (
-            new_x.max(0.0).min(self.width - 1.0),
-            new_y.max(0.0).min(self.height - 1.0),
+            new_x.max(0.0).min(self.width - self.edge_margin),
+            new_y.max(0.0).min(self.height - self.edge_margin),
         )
     }
 
     fn distance(&self, pa: (f64, f64), pb: (f64, f64)) -> f64 {
-        let dx = pa.0 - pb.0;
-        let dy = pa.1 - pb.1;
-        (dx * dx + dy * dy).sqrt()
+        let mut d = (pa.0 - pb.0, pa.1 - pb.1);
+        if self.periodic {
+            // Distance to the nearest image
+            d = nearest_image(d, (self.width, self.height));
+        }
+        (d.0 * d.0 + d.1 * d.1).sqrt()
+    }
+
+    /// Returns the grid lines within `reach` of `idx`, wrapping around in a periodic box.
+    fn neighbor_lines(&self, idx: f64, reach: f64, count: f64) -> Vec<usize> {
+        if self.periodic {
+            let (idx, reach, count) = (idx as isize, reach as isize, count as isize);
+            (idx - reach..=idx + reach)
+                .map(|i| i.rem_euclid(count) as usize)
+                .collect()
+        } else {
+            let start = (idx - reach).max(0.0) as usize;
+            let end = (idx + reach).min(count - 1.0) as usize;
+            (start..=end).collect()
+        }
     }
 
     fn is_valid(&self, point: (f64, f64)) -> bool {
-        let xidx = (point.0 / self.cell_size).floor();
-        let yidx = (point.1 / self.cell_size).floor();
+        if !self.in_domain(point) {
+            return false;
+        }
+
+        let (xidx, yidx) = self.cell_of(point);
 
-        // Determine the neighborhood around the source point.
-        let start_x = (xidx - 2.0).max(0.0) as usize;
-        let end_x = (xidx + 2.0).min(self.grid_width - 1.0) as usize;
-        let start_y = (yidx - 2.0).max(0.0) as usize;
-        let end_y = (yidx + 2.0).min(self.grid_height - 1.0) as usize;
+        // Determine the neighborhood around the source point, wide enough for the largest radius.
+        let reach_x = (self.max_distance / self.cell_size.0).ceil();
+        let reach_y = (self.max_distance / self.cell_size.1).ceil();
+        let columns = self.neighbor_lines(xidx, reach_x, self.grid_width);
+        let rows = self.neighbor_lines(yidx, reach_y, self.grid_height);
+        let radius = (self.radius)(point);
 
         // Check all non-empty neighbors cells and make sure the new point is outside their radius.
-        for y in start_y..end_y {
-            for x in start_x..end_x {
+        for &y in &rows {
+            for &x in &columns {
                 let idx = y * self.grid_width as usize + x;
                 if let Some(cell) = self.grid[idx] {
-                    if self.distance(cell, point) <= self.minimum_distance {
+                    let minimum_distance = radius.max((self.radius)(cell));
+                    if self.distance(cell, point) <= minimum_distance {
                         return false;
                     }
                 }
@@ -99,15 +204,14 @@ impl PoissonDisk {
         true
     }
 
-    fn generate(&mut self) {
-        let mut rng = rand::thread_rng();
+    fn generate<R: Rng>(&mut self, rng: &mut R) {
         while !self.active.is_empty() {
             let idx = (rng.gen::<f64>() * (self.active.len() - 1) as f64) as usize;
             let source = self.active[idx];
             let mut found = false;
 
             for _ in 0..self.num_samples {
-                let new_point = self.generate_around(source);
+                let new_point = self.generate_around(source, rng);
 
                 if self.is_valid(new_point) {
                     self.insert_point(new_point);
@@ -123,8 +227,364 @@ impl PoissonDisk {
     }
 }
 
-pub fn generate_points(d: f64, width: f64, height: f64) -> Vec<(f64, f64)> {
-    let mut poisson_disk = PoissonDisk::new(width, height, d, 30);
-    poisson_disk.generate();
+/// Poisson disk sampling of the `width * height` box, which wraps around when `periodic`.
+/// Candidates outside `domain` are rejected.
+pub fn generate_points<R: Rng>(
+    d: f64,
+    width: f64,
+    height: f64,
+    periodic: bool,
+    domain: Option<&Polygon>,
+    rng: &mut R,
+) -> Vec<(f64, f64)> {
+    let mut poisson_disk = PoissonDisk::new(width, height, |_| d, (d, d), periodic, domain);
+    poisson_disk.start(rng);
+    poisson_disk.generate(rng);
+    poisson_disk.samples
+}
+
+/// Poisson disk sampling of the `width * height` box growing from `seeds`, which are part of the
+/// returned points. Seeds must be at least `d` apart, and candidates outside `domain` are
+/// rejected.
+pub fn generate_points_from<R: Rng>(
+    d: f64,
+    width: f64,
+    height: f64,
+    seeds: &[(f64, f64)],
+    domain: Option<&Polygon>,
+    rng: &mut R,
+) -> Vec<(f64, f64)> {
+    let mut poisson_disk = PoissonDisk::new(width, height, |_| d, (d, d), false, domain);
+    // Keep candidates up to the far edges, so that seeds sampled along them aren't left alone
+    poisson_disk.edge_margin = 0.0;
+    poisson_disk.start_from(seeds);
+    poisson_disk.generate(rng);
+    poisson_disk.samples
+}
+
+/// Poisson disk sampling where the minimum distance around a point is `sizing(point)`, clamped
+/// to `[min_distance, max_distance]`. Distances which aren't positive and finite, such as NaN
+/// outside the domain of an expression, are replaced by `max_distance`.
+///
+/// Two points are at least the larger of their distances apart. Candidates outside `domain` are
+/// rejected.
+pub fn generate_points_with_sizing<R: Rng, F: Fn((f64, f64)) -> f64>(
+    sizing: F,
+    (min_distance, max_distance): (f64, f64),
+    width: f64,
+    height: f64,
+    periodic: bool,
+    domain: Option<&Polygon>,
+    rng: &mut R,
+) -> Vec<(f64, f64)> {
+    let radius = |p| match sizing(p) {
+        distance if distance.is_finite() && distance > 0.0 => {
+            distance.clamp(min_distance, max_distance)
+        }
+        _ => max_distance,
+    };
+    let mut poisson_disk = PoissonDisk::new(
+        width,
+        height,
+        radius,
+        (min_distance, max_distance),
+        periodic,
+        domain,
+    );
+    poisson_disk.start(rng);
+    poisson_disk.generate(rng);
     poisson_disk.samples
 }
+
+/// 3D equivalent of `PoissonDisk`, with a constant minimum distance.
+struct PoissonDisk3d {
+    radius: f64,
+    num_samples: usize,
+    size: (f64, f64, f64),
+    cell_size: f64,
+    grid_size: (usize, usize, usize),
+    grid: Vec<Option<(f64, f64, f64)>>,
+    samples: Vec<(f64, f64, f64)>,
+    active: Vec<(f64, f64, f64)>,
+}
+
+impl PoissonDisk3d {
+    fn new(w: f64, h: f64, depth: f64, radius: f64) -> Self {
+        // At most one point per cell, the cell diagonal being the radius
+        let cell_size = radius / 3.0_f64.sqrt();
+        let grid_size = (
+            (w / cell_size).ceil() as usize + 1,
+            (h / cell_size).ceil() as usize + 1,
+            (depth / cell_size).ceil() as usize + 1,
+        );
+
+        PoissonDisk3d {
+            radius,
+            num_samples: NUM_SAMPLES,
+            size: (w, h, depth),
+            cell_size,
+            grid_size,
+            grid: vec![None; grid_size.0 * grid_size.1 * grid_size.2],
+            samples: Vec::new(),
+            active: Vec::new(),
+        }
+    }
+
+    fn cell(&self, point: (f64, f64, f64)) -> (usize, usize, usize) {
+        (
+            (point.0 / self.cell_size).floor() as usize,
+            (point.1 / self.cell_size).floor() as usize,
+            (point.2 / self.cell_size).floor() as usize,
+        )
+    }
+
+    fn insert_point(&mut self, point: (f64, f64, f64)) {
+        let (x, y, z) = self.cell(point);
+        let idx = (z * self.grid_size.1 + y) * self.grid_size.0 + x;
+        self.grid[idx] = Some(point);
+        self.active.push(point);
+        self.samples.push(point);
+    }
+
+    fn generate_around<R: Rng>(&self, pt: (f64, f64, f64), rng: &mut R) -> (f64, f64, f64) {
+        // Random direction on the sphere and radius between r and 2r
+        let cos_theta = 2.0 * rng.gen::<f64>() - 1.0;
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = 2.0 * std::f64::consts::PI * rng.gen::<f64>();
+        let radius = self.radius * (rng.gen::<f64>() + 1.0);
+
+        (
+            (pt.0 + radius * sin_theta * phi.cos()).clamp(0.0, self.size.0),
+            (pt.1 + radius * sin_theta * phi.sin()).clamp(0.0, self.size.1),
+            (pt.2 + radius * cos_theta).clamp(0.0, self.size.2),
+        )
+    }
+
+    fn is_valid(&self, point: (f64, f64, f64)) -> bool {
+        let (xidx, yidx, zidx) = self.cell(point);
+        // The radius spans ceil(sqrt(3)) cells
+        let reach = 2;
+        let lines =
+            |idx: usize, count: usize| idx.saturating_sub(reach)..=(idx + reach).min(count - 1);
+
+        for z in lines(zidx, self.grid_size.2) {
+            for y in lines(yidx, self.grid_size.1) {
+                for x in lines(xidx, self.grid_size.0) {
+                    let idx = (z * self.grid_size.1 + y) * self.grid_size.0 + x;
+                    if let Some(cell) = self.grid[idx] {
+                        let distance = ((cell.0 - point.0).powi(2)
+                            + (cell.1 - point.1).powi(2)
+                            + (cell.2 - point.2).powi(2))
+                        .sqrt();
+                        if distance <= self.radius {
+                            return false;
+                        }
+                    }
+                }
+            }
+        }
+
+        true
+    }
+
+    fn generate<R: Rng>(&mut self, rng: &mut R) {
+        let (w, h, depth) = self.size;
+        self.insert_point((
+            rng.gen::<f64>() * w,
+            rng.gen::<f64>() * h,
+            rng.gen::<f64>() * depth,
+        ));
+
+        while !self.active.is_empty() {
+            let idx = rng.gen_range(0..self.active.len());
+            let source = self.active[idx];
+            let mut found = false;
+
+            for _ in 0..self.num_samples {
+                let new_point = self.generate_around(source, rng);
+
+                if self.is_valid(new_point) {
+                    self.insert_point(new_point);
+                    found = true;
+                }
+            }
+            if !found {
+                self.active.swap_remove(idx);
+            }
+        }
+    }
+}
+
+/// Poisson disk sampling of the `width * height * depth` box.
+pub fn generate_points_3d<R: Rng>(
+    d: f64,
+    width: f64,
+    height: f64,
+    depth: f64,
+    rng: &mut R,
+) -> Vec<(f64, f64, f64)> {
+    let mut poisson_disk = PoissonDisk3d::new(width, height, depth, d);
+    poisson_disk.generate(rng);
+    poisson_disk.samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_same_seed_same_points() {
+        let points1 = generate_points(1.0, 10.0, 10.0, false, None, &mut StdRng::seed_from_u64(42));
+        let points2 = generate_points(1.0, 10.0, 10.0, false, None, &mut StdRng::seed_from_u64(42));
+        let points3 = generate_points(1.0, 10.0, 10.0, false, None, &mut StdRng::seed_from_u64(43));
+
+        assert!(!points1.is_empty());
+        assert_eq!(points1, points2);
+        assert_ne!(points1, points3);
+    }
+
+    #[test]
+    fn test_minimum_distance() {
+        // Neighbor cells are scanned up to the last row and column within reach
+        let points = generate_points(1.0, 20.0, 20.0, false, None, &mut StdRng::seed_from_u64(42));
+
+        for (i, &a) in points.iter().enumerate() {
+            for &b in &points[i + 1..] {
+                assert!(((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt() > 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sizing() {
+        // Points get sparser from left to right
+        let sizing = |(x, _): (f64, f64)| 0.5 + 0.15 * x;
+        let points = generate_points_with_sizing(
+            sizing,
+            (0.5, 2.0),
+            10.0,
+            10.0,
+            false,
+            None,
+            &mut StdRng::seed_from_u64(42),
+        );
+
+        for (i, &a) in points.iter().enumerate() {
+            for &b in &points[i + 1..] {
+                let distance = ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt();
+                assert!(distance > sizing(a).max(sizing(b)));
+            }
+        }
+        let left = points.iter().filter(|p| p.0 < 3.0).count();
+        let right = points.iter().filter(|p| p.0 > 6.0).count();
+        assert!(left > 2 * right);
+    }
+
+    #[test]
+    fn test_invalid_sizing() {
+        // NaN on the left half of the box, negative on the right one
+        let sizing = |(x, _): (f64, f64)| if x < 5.0 { f64::NAN } else { -1.0 };
+        let points = generate_points_with_sizing(
+            sizing,
+            (0.5, 2.0),
+            10.0,
+            10.0,
+            false,
+            None,
+            &mut StdRng::seed_from_u64(42),
+        );
+
+        assert!(!points.is_empty());
+        for (i, &a) in points.iter().enumerate() {
+            for &b in &points[i + 1..] {
+                assert!(((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt() > 2.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_periodic() {
+        let (width, height) = (10.0, 6.0);
+        let points = generate_points(
+            1.0,
+            width,
+            height,
+            true,
+            None,
+            &mut StdRng::seed_from_u64(42),
+        );
+
+        // Points are also far enough from the images of the others across the edges
+        for (i, &a) in points.iter().enumerate() {
+            assert!((0.0..width).contains(&a.0) && (0.0..height).contains(&a.1));
+            for &b in &points[i + 1..] {
+                let dx = (a.0 - b.0).abs().min(width - (a.0 - b.0).abs());
+                let dy = (a.1 - b.1).abs().min(height - (a.1 - b.1).abs());
+                assert!((dx * dx + dy * dy).sqrt() > 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_periodic_partial_cells() {
+        // Neither side is a multiple of the d / √2 cells, the last ones being thin
+        let (width, height) = (10.1, 6.5);
+        for seed in 0..10 {
+            let points = generate_points(
+                1.0,
+                width,
+                height,
+                true,
+                None,
+                &mut StdRng::seed_from_u64(seed),
+            );
+
+            for (i, &a) in points.iter().enumerate() {
+                for &b in &points[i + 1..] {
+                    let dx = (a.0 - b.0).abs().min(width - (a.0 - b.0).abs());
+                    let dy = (a.1 - b.1).abs().min(height - (a.1 - b.1).abs());
+                    assert!((dx * dx + dy * dy).sqrt() > 1.0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_domain() {
+        // Triangle with a square hole
+        let domain = Polygon::new(
+            vec![(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)],
+            vec![vec![(1.0, 1.0), (3.0, 1.0), (3.0, 3.0), (1.0, 3.0)]],
+        )
+        .unwrap();
+        let points = generate_points(
+            0.5,
+            10.0,
+            10.0,
+            false,
+            Some(&domain),
+            &mut StdRng::seed_from_u64(42),
+        );
+
+        assert!(points.len() > 50);
+        assert!(points.iter().all(|&p| domain.contains(p)));
+    }
+
+    #[test]
+    fn test_3d() {
+        let d = 1.0;
+        let points = generate_points_3d(d, 5.0, 4.0, 3.0, &mut StdRng::seed_from_u64(42));
+
+        // Roughly as dense as a random close packing of spheres of diameter d
+        assert!(points.len() > 40);
+        for (i, &a) in points.iter().enumerate() {
+            assert!(a.0 <= 5.0 && a.1 <= 4.0 && a.2 <= 3.0);
+            for &b in &points[i + 1..] {
+                let distance =
+                    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)).sqrt();
+                assert!(distance > d);
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+// Parallel Poisson-disk sampling, following the phase-group approach of
+// https://doi.org/10.1145/1360612.1360619 (Wei, Parallel Poisson disk sampling)
+
+use rand::prelude::*;
+use rayon::prelude::*;
+
+/// Number of cells handed to one task, each chunk draws from its own RNG stream.
+const CHUNK_SIZE: usize = 1024;
+
+struct ParallelPoissonDisk {
+    minimum_distance: f64,
+    num_samples: usize,
+    width: f64,
+    height: f64,
+    cell_size: f64,
+    grid_width: usize,
+    grid_height: usize,
+    grid: Vec<Option<(f64, f64)>>,
+}
+
+impl ParallelPoissonDisk {
+    fn new(w: f64, h: f64, r: f64, k: usize) -> Self {
+        // With a cell diagonal of r, each cell holds at most one sample
+        let cell_size = r / 2.0_f64.sqrt();
+        let grid_width = (w / cell_size).ceil().max(1.0) as usize;
+        let grid_height = (h / cell_size).ceil().max(1.0) as usize;
+
+        ParallelPoissonDisk {
+            minimum_distance: r,
+            num_samples: k,
+            width: w,
+            height: h,
+            cell_size,
+            grid_width,
+            grid_height,
+            grid: vec![None; grid_width * grid_height],
+        }
+    }
+
+    /// Splits the grid into 3*3 phase groups.
+    ///
+    /// Two cells of the same group are at least 3 cells apart on one axis, so samples placed in
+    /// them are more than `2 * cell_size = sqrt(2) * r` apart and never conflict. Each group can
+    /// then be processed concurrently, only reading cells that no other task is writing.
+    fn phase_groups(&self) -> Vec<Vec<usize>> {
+        let mut phases = vec![Vec::new(); 9];
+        for y in 0..self.grid_height {
+            for x in 0..self.grid_width {
+                phases[(y % 3) * 3 + x % 3].push(y * self.grid_width + x);
+            }
+        }
+        phases
+    }
+
+    fn distance(&self, pa: (f64, f64), pb: (f64, f64)) -> f64 {
+        let dx = pa.0 - pb.0;
+        let dy = pa.1 - pb.1;
+        (dx * dx + dy * dy).sqrt()
+    }
+
+    fn is_valid(&self, point: (f64, f64), cell_x: usize, cell_y: usize) -> bool {
+        // Samples at most r away can only be within 2 cells of the candidate's
+        let start_x = cell_x.saturating_sub(2);
+        let end_x = (cell_x + 2).min(self.grid_width - 1);
+        let start_y = cell_y.saturating_sub(2);
+        let end_y = (cell_y + 2).min(self.grid_height - 1);
+
+        for y in start_y..=end_y {
+            for x in start_x..=end_x {
+                if let Some(cell) = self.grid[y * self.grid_width + x] {
+                    if self.distance(cell, point) <= self.minimum_distance {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Throws one dart in the cell `idx`, returning it if it is far enough from every sample.
+    fn try_cell<R: Rng>(&self, idx: usize, rng: &mut R) -> Option<(f64, f64)> {
+        let cell_x = idx % self.grid_width;
+        let cell_y = idx / self.grid_width;
+
+        let x = (cell_x as f64 + rng.gen::<f64>()) * self.cell_size;
+        let y = (cell_y as f64 + rng.gen::<f64>()) * self.cell_size;
+
+        // The last row and column of cells may overflow the box
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let point = (x, y);
+        self.is_valid(point, cell_x, cell_y).then_some(point)
+    }
+
+    fn generate<R: Rng>(&mut self, rng: &mut R) {
+        let mut phases = self.phase_groups();
+
+        for _ in 0..self.num_samples {
+            phases.shuffle(rng);
+
+            for phase in phases.iter_mut() {
+                let seed = rng.gen::<u64>();
+
+                // Cells of a phase don't interact, so they all read the grid as it was before
+                // the phase and their samples are written back afterwards
+                let found: Vec<(usize, (f64, f64))> = phase
+                    .par_chunks(CHUNK_SIZE)
+                    .enumerate()
+                    .flat_map_iter(|(chunk_idx, cells)| {
+                        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(chunk_idx as u64));
+                        cells
+                            .iter()
+                            .filter_map(|&idx| Some((idx, self.try_cell(idx, &mut rng)?)))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect();
+
+                for (idx, point) in found {
+                    self.grid[idx] = Some(point);
+                }
+
+                // Filled cells can't receive another sample
+                phase.retain(|&idx| self.grid[idx].is_none());
+            }
+        }
+    }
+}
+
+pub fn generate_points<R: Rng>(d: f64, width: f64, height: f64, rng: &mut R) -> Vec<(f64, f64)> {
+    let mut poisson_disk = ParallelPoissonDisk::new(width, height, d, 30);
+    poisson_disk.generate(rng);
+    poisson_disk.grid.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minimum_distance() {
+        let d = 1.0;
+        let points = generate_points(d, 20.0, 10.0, &mut StdRng::seed_from_u64(0));
+
+        assert!(!points.is_empty());
+        for (i, &(x1, y1)) in points.iter().enumerate() {
+            assert!((0.0..20.0).contains(&x1) && (0.0..10.0).contains(&y1));
+            for &(x2, y2) in &points[i + 1..] {
+                let distance = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+                assert!(
+                    distance > d,
+                    "Points ({}, {}) and ({}, {}) are too close",
+                    x1,
+                    y1,
+                    x2,
+                    y2
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_points() {
+        let points1 = generate_points(0.5, 10.0, 10.0, &mut StdRng::seed_from_u64(42));
+        let points2 = generate_points(0.5, 10.0, 10.0, &mut StdRng::seed_from_u64(42));
+
+        assert_eq!(points1, points2);
+    }
+}
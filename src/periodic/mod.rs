@@ -0,0 +1,20 @@
+// Boxes wrapping around, opposite edges being neighbors.
+
+/// Returns the shortest image of the vector `(dx, dy)` in a box of size `period`.
+pub fn nearest_image((dx, dy): (f64, f64), period: (f64, f64)) -> (f64, f64) {
+    (
+        dx - period.0 * (dx / period.0).round(),
+        dy - period.1 * (dy / period.1).round(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_image() {
+        assert_eq!(nearest_image((9.0, -1.0), (10.0, 4.0)), (-1.0, -1.0));
+        assert_eq!(nearest_image((-7.0, 3.0), (10.0, 4.0)), (3.0, -1.0));
+    }
+}
@@ -1,27 +1,42 @@
+use std::path::Path;
+
 use plotly::common::{ColorScalePalette, Mode};
 use plotly::{HeatMap, Layout, Plot, Scatter};
 
 use rand::seq::SliceRandom; // Requires the `rand` crate
-use rand::thread_rng;
+use rand::Rng;
+
+use crate::distance::DistanceField;
 
-pub fn plot_heatmap_with_points(
+/// Opens the plot in a browser, or writes it to a self-contained HTML file at `output`.
+fn show_or_write(plot: &Plot, output: Option<&Path>) {
+    match output {
+        Some(path) => plot.write_html(path),
+        None => plot.show(),
+    }
+}
+
+pub fn plot_heatmap_with_points<R: Rng>(
     data: &[usize],
+    reso: (usize, usize),
     points: &[(f64, f64)],
     config_dimension: (f64, f64),
+    rng: &mut R,
+    output: Option<&Path>,
 ) {
-    let reso = (data.len() as f64).sqrt() as usize;
+    let (reso_x, reso_y) = reso;
 
-    // Reshape the data into a 2D grid (Vec<Vec<usize>>)
-    let mut grid: Vec<Vec<usize>> = vec![vec![0; reso]; reso];
-    for i in 0..reso {
-        for j in 0..reso {
-            grid[i][j] = data[i * reso + j];
+    // Reshape the data into a 2D grid (Vec<Vec<usize>>), one row per pixel line
+    let mut grid: Vec<Vec<usize>> = vec![vec![0; reso_x]; reso_y];
+    for i in 0..reso_y {
+        for j in 0..reso_x {
+            grid[i][j] = data[i * reso_x + j];
         }
     }
 
     // Generate the range of colors and shuffle them
     let mut colors: Vec<usize> = (1..=points.len()).collect();
-    colors.shuffle(&mut thread_rng());
+    colors.shuffle(rng);
 
     // Create a mapping from region value to shuffled color index
     let value_to_color: Vec<usize> = colors;
@@ -31,7 +46,10 @@ pub fn plot_heatmap_with_points(
         .iter()
         .map(|row| {
             row.iter()
-                .map(|&value| value_to_color[value - 1] as f64) // Map based on the shuffled color
+                .map(|&value| match value {
+                    0 => f64::NAN,                         // Outside the domain, left blank
+                    _ => value_to_color[value - 1] as f64, // Map based on the shuffled color
+                })
                 .collect()
         })
         .collect();
@@ -43,8 +61,8 @@ pub fn plot_heatmap_with_points(
         .iter()
         .map(|(px, py)| {
             (
-                *px * reso as f64 / config_dimension.0,
-                *py * reso as f64 / config_dimension.1,
+                *px * reso_x as f64 / config_dimension.0,
+                *py * reso_y as f64 / config_dimension.1,
             )
         }) // Scale points to match the heatmap resolution
         .collect();
@@ -60,13 +78,56 @@ pub fn plot_heatmap_with_points(
     plot.add_trace(heatmap);
     plot.add_trace(scatter);
 
-    let layout = Layout::new().height(2048).width(2048).auto_size(false);
+    // Keep the aspect ratio of the grid so that cells aren't stretched
+    let height = (2048 * reso_y / reso_x).max(1);
+    let layout = Layout::new().height(height).width(2048).auto_size(false);
+    plot.set_layout(layout);
+
+    show_or_write(&plot, output);
+}
+
+pub fn plot_distance_field(
+    field: &DistanceField,
+    points: &[(f64, f64)],
+    config_dimension: (f64, f64),
+    output: Option<&Path>,
+) {
+    let (reso_x, reso_y) = field.reso;
+
+    // One row per pixel line, as for the cells
+    let grid: Vec<Vec<f64>> = field
+        .distances
+        .chunks(reso_x)
+        .map(|row| row.to_vec())
+        .collect();
+
+    let heatmap = HeatMap::new_z(grid).color_scale(ColorScalePalette::Viridis.into());
+
+    // Pixel centers are half a pixel away from their index
+    let x_list: Vec<f64> = points
+        .iter()
+        .map(|(px, _)| *px * reso_x as f64 / config_dimension.0 - 0.5)
+        .collect();
+    let y_list: Vec<f64> = points
+        .iter()
+        .map(|(_, py)| *py * reso_y as f64 / config_dimension.1 - 0.5)
+        .collect();
+    let scatter = Scatter::new(x_list, y_list)
+        .mode(Mode::Markers)
+        .marker(plotly::common::Marker::new().size(10).color("#FFFFFF")); // White markers for points
+
+    let mut plot = Plot::new();
+    plot.add_trace(heatmap);
+    plot.add_trace(scatter);
+
+    let height = (2048 * reso_y / reso_x).max(1);
+    let layout = Layout::new().height(height).width(2048).auto_size(false);
     plot.set_layout(layout);
 
-    plot.show();
+    show_or_write(&plot, output);
 }
 
-pub fn plot_points(points: &[(f64, f64)]) {
+pub fn plot_points(points: &[(f64, f64)], output: Option<&Path>) {
     let x_list = points.iter().map(|(x, _)| *x).collect();
     let y_list = points.iter().map(|(_, y)| *y).collect();
     let mut plot = Plot::new();
@@ -78,5 +139,5 @@ pub fn plot_points(points: &[(f64, f64)]) {
     let layout = Layout::new().height(2048).width(2048).auto_size(false);
 
     plot.set_layout(layout);
-    plot.show();
+    show_or_write(&plot, output);
 }
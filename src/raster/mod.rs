@@ -0,0 +1,278 @@
+// JFA label grids written for other tools, as images or NumPy arrays.
+//
+// Labels are 0 outside the domain and `i + 1` for the seed `i`. Images have their first row at
+// the top of the box, while arrays keep the row order of the grid, `labels[y, x]` with `y`
+// going up.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Returns the rows of `labels` from the top of the box to its bottom.
+fn top_down_rows<T>(labels: &[T], reso: (usize, usize)) -> impl Iterator<Item = &[T]> {
+    labels.chunks(reso.0).rev().take(reso.1)
+}
+
+fn check_size(labels: &[usize], reso: (usize, usize)) -> Result<(), &'static str> {
+    if labels.len() != reso.0 * reso.1 || labels.is_empty() {
+        return Err("Label grid size doesn't match the resolution");
+    }
+    Ok(())
+}
+
+/// Writes each label with its color in `colors`, white outside the domain.
+///
+/// With at most 255 seeds, the image is indexed and the index of a pixel is its label.
+pub fn write_colored_png(
+    path: &Path,
+    labels: &[usize],
+    reso: (usize, usize),
+    colors: &[[u8; 3]],
+) -> Result<(), &'static str> {
+    check_size(labels, reso)?;
+    let file = File::create(path).map_err(|_| "Unable to create the label image")?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), reso.0 as u32, reso.1 as u32);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let data: Vec<u8> = if colors.len() <= 255 {
+        let mut palette = vec![255, 255, 255];
+        palette.extend(colors.iter().flatten());
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_palette(palette);
+        top_down_rows(labels, reso)
+            .flatten()
+            .map(|&label| label as u8)
+            .collect()
+    } else {
+        encoder.set_color(png::ColorType::Rgb);
+        top_down_rows(labels, reso)
+            .flatten()
+            .flat_map(|&label| match label {
+                0 => [255, 255, 255],
+                label => colors[label - 1],
+            })
+            .collect()
+    };
+
+    let mut writer = encoder
+        .write_header()
+        .map_err(|_| "Unable to write the label image")?;
+    writer
+        .write_image_data(&data)
+        .map_err(|_| "Unable to write the label image")
+}
+
+/// Writes the labels as a 16-bit grayscale PNG, which holds up to 65535 seeds.
+pub fn write_gray16_png(
+    path: &Path,
+    labels: &[usize],
+    reso: (usize, usize),
+) -> Result<(), &'static str> {
+    check_size(labels, reso)?;
+    if labels.iter().any(|&label| label > u16::MAX as usize) {
+        return Err("Too many seeds for a 16-bit label image");
+    }
+    let data: Vec<u16> = top_down_rows(labels, reso)
+        .flatten()
+        .map(|&label| label as u16)
+        .collect();
+    image::ImageBuffer::<image::Luma<u16>, _>::from_raw(reso.0 as u32, reso.1 as u32, data)
+        .unwrap()
+        .save(path)
+        .map_err(|_| "Unable to write the label image")
+}
+
+/// Writes the labels as a 32-bit unsigned grayscale TIFF, since PNG stops at 16 bits. The path
+/// must end in `.tif` or `.tiff`.
+pub fn write_gray32_tiff(
+    path: &Path,
+    labels: &[usize],
+    reso: (usize, usize),
+) -> Result<(), &'static str> {
+    let is_tiff = path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("tif") || extension.eq_ignore_ascii_case("tiff")
+    });
+    if !is_tiff {
+        return Err("32-bit label images are TIFF files, use a .tif or .tiff extension");
+    }
+    check_size(labels, reso)?;
+    if labels.iter().any(|&label| label > u32::MAX as usize) {
+        return Err("Too many seeds for a 32-bit label image");
+    }
+    let data_size = labels.len() * 4;
+    if data_size > u32::MAX as usize {
+        return Err("Label grid is too large for a TIFF file");
+    }
+
+    // Little-endian header, followed by a single directory and a single strip of pixels
+    const SHORT: u16 = 3;
+    const LONG: u16 = 4;
+    let entries: [(u16, u16, u32); 10] = [
+        (256, LONG, reso.0 as u32),       // Image width
+        (257, LONG, reso.1 as u32),       // Image length
+        (258, SHORT, 32),                 // Bits per sample
+        (259, SHORT, 1),                  // No compression
+        (262, SHORT, 1),                  // Black is zero
+        (273, LONG, 8 + 2 + 12 * 10 + 4), // Strip offset, right after the directory
+        (277, SHORT, 1),                  // Samples per pixel
+        (278, LONG, reso.1 as u32),       // Rows per strip
+        (279, LONG, data_size as u32),    // Strip byte count
+        (339, SHORT, 1),                  // Unsigned integer samples
+    ];
+
+    let file = File::create(path).map_err(|_| "Unable to create the label image")?;
+    let mut file = BufWriter::new(file);
+    let mut write = || -> std::io::Result<()> {
+        file.write_all(b"II")?;
+        file.write_all(&42u16.to_le_bytes())?;
+        file.write_all(&8u32.to_le_bytes())?;
+        file.write_all(&(entries.len() as u16).to_le_bytes())?;
+        for (tag, kind, value) in entries {
+            file.write_all(&tag.to_le_bytes())?;
+            file.write_all(&kind.to_le_bytes())?;
+            file.write_all(&1u32.to_le_bytes())?;
+            // Shorts are left-justified in the value field, which little-endian does for us
+            file.write_all(&value.to_le_bytes())?;
+        }
+        file.write_all(&0u32.to_le_bytes())?;
+        for row in top_down_rows(labels, reso) {
+            for &label in row {
+                file.write_all(&(label as u32).to_le_bytes())?;
+            }
+        }
+        file.flush()
+    };
+    write().map_err(|_| "Unable to write the label image")
+}
+
+/// Writes a NumPy `.npy` array of little-endian `u32` labels with the given `shape`, the last
+/// axis varying the fastest.
+pub fn write_npy(path: &Path, labels: &[u32], shape: &[usize]) -> Result<(), &'static str> {
+    if shape.iter().product::<usize>() != labels.len() {
+        return Err("Label grid size doesn't match its shape");
+    }
+
+    let shape = match shape {
+        [n] => format!("({},)", n),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!(
+        "{{'descr': '<u4', 'fortran_order': False, 'shape': {}, }}",
+        shape
+    );
+    // The magic string, version and header length take 10 bytes, and the data is aligned on 64
+    let padding = 63 - (10 + header.len()) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    let file = File::create(path).map_err(|_| "Unable to create the label array")?;
+    let mut file = BufWriter::new(file);
+    let mut write = || -> std::io::Result<()> {
+        file.write_all(b"\x93NUMPY\x01\x00")?;
+        file.write_all(&(header.len() as u16).to_le_bytes())?;
+        file.write_all(header.as_bytes())?;
+        for label in labels {
+            file.write_all(&label.to_le_bytes())?;
+        }
+        file.flush()
+    };
+    write().map_err(|_| "Unable to write the label array")
+}
+
+/// Writes the 2D label grid as a NumPy array of shape `(reso.1, reso.0)`.
+pub fn write_npy_2d(
+    path: &Path,
+    labels: &[usize],
+    reso: (usize, usize),
+) -> Result<(), &'static str> {
+    check_size(labels, reso)?;
+    let labels: Vec<u32> = labels
+        .iter()
+        .map(|&label| u32::try_from(label).map_err(|_| "Too many seeds for a u32 label array"))
+        .collect::<Result<_, _>>()?;
+    write_npy(path, &labels, &[reso.1, reso.0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_npy() {
+        let path = std::env::temp_dir().join("blue_noise_test_labels.npy");
+        write_npy_2d(&path, &[1, 2, 3, 0, 2, 2], (3, 2)).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        let header = std::str::from_utf8(&bytes[10..10 + header_len]).unwrap();
+        assert!(header.contains("'shape': (2, 3)"));
+        assert!(header.ends_with('\n'));
+
+        let labels: Vec<u32> = bytes[10 + header_len..]
+            .chunks(4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(labels, vec![1, 2, 3, 0, 2, 2]);
+    }
+
+    #[test]
+    fn test_write_gray16_png() {
+        let path = std::env::temp_dir().join("blue_noise_test_labels.png");
+        write_gray16_png(&path, &[1, 2, 300, 0], (2, 2)).unwrap();
+        let image = image::open(&path).unwrap().into_luma16();
+        std::fs::remove_file(path).unwrap();
+
+        // The top row of the image is the last row of the grid
+        assert_eq!(image.as_raw(), &vec![300, 0, 1, 2]);
+    }
+
+    #[test]
+    fn test_write_gray32_tiff() {
+        let path = std::env::temp_dir().join("blue_noise_test_labels.tif");
+        write_gray32_tiff(&path, &[1, 2, 3, 0, 70000, 2], (3, 2)).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let short = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+        let long = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        assert_eq!(&bytes[..2], b"II");
+        assert_eq!(short(2), 42);
+        assert_eq!(long(4), 8);
+
+        // Directory entries are sorted by tag, each with a single value
+        assert_eq!(short(8), 10);
+        let entry = |tag: u16| {
+            (0..10)
+                .map(|i| 10 + 12 * i)
+                .find(|&at| short(at) == tag)
+                .map(|at| long(at + 8))
+                .unwrap()
+        };
+        assert_eq!(entry(256), 3);
+        assert_eq!(entry(257), 2);
+        assert_eq!(entry(258), 32);
+        assert_eq!(entry(339), 1);
+        assert_eq!(entry(273), 134);
+
+        // The top row of the image is the last row of the grid
+        let labels: Vec<u32> = bytes[134..]
+            .chunks(4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(labels, vec![0, 70000, 2, 1, 2, 3]);
+
+        let png = std::env::temp_dir().join("blue_noise_test_labels_gray32.png");
+        assert!(write_gray32_tiff(&png, &[1], (1, 1)).is_err());
+        assert!(!png.exists());
+    }
+}
@@ -0,0 +1,262 @@
+// Plots rendered without a browser, as PNG images or SVG drawings.
+//
+// Images and drawings have their first row at the top of the box, and cells get the same
+// shuffled Viridis colors as in the interactive plots.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use image::{Rgb, RgbImage};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::distance::DistanceField;
+use crate::domain::Polygon;
+
+/// Size of the longest side of point plots, in pixels.
+const POINTS_SIZE: usize = 1024;
+
+/// Samples of the Viridis color map, evenly spaced.
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [71, 44, 122],
+    [59, 81, 139],
+    [44, 113, 142],
+    [33, 144, 141],
+    [39, 173, 129],
+    [92, 200, 99],
+    [170, 220, 50],
+    [253, 231, 37],
+];
+
+/// Plot files, told apart by their extension.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Format {
+    Html,
+    Png,
+    Svg,
+}
+
+pub fn format(path: &Path) -> Result<Format, &'static str> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("html") | Some("htm") => Ok(Format::Html),
+        Some("png") => Ok(Format::Png),
+        Some("svg") => Ok(Format::Svg),
+        _ => Err("Plots can only be saved as HTML, PNG or SVG files"),
+    }
+}
+
+/// Returns the Viridis color at `t` in `[0, 1]`.
+pub fn viridis(t: f64) -> [u8; 3] {
+    let t = t.clamp(0.0, 1.0) * (VIRIDIS.len() - 1) as f64;
+    let i = (t as usize).min(VIRIDIS.len() - 2);
+    let f = t - i as f64;
+    let mix = |a: u8, b: u8| (a as f64 + f * (b as f64 - a as f64)).round() as u8;
+    let (a, b) = (VIRIDIS[i], VIRIDIS[i + 1]);
+    [mix(a[0], b[0]), mix(a[1], b[1]), mix(a[2], b[2])]
+}
+
+/// Returns a color per seed, spread over the color map in a random order so that neighboring
+/// cells are told apart.
+pub fn label_colors<R: Rng>(num_points: usize, rng: &mut R) -> Vec<[u8; 3]> {
+    let mut order: Vec<usize> = (0..num_points).collect();
+    order.shuffle(rng);
+    let last = num_points.saturating_sub(1).max(1) as f64;
+    order.iter().map(|&i| viridis(i as f64 / last)).collect()
+}
+
+/// Draws a disc of `radius` pixels around `center`, given in pixels from the top left corner.
+fn draw_disc(image: &mut RgbImage, center: (f64, f64), radius: f64, color: [u8; 3]) {
+    let (width, height) = image.dimensions();
+    let min_x = (center.0 - radius).floor().max(0.0) as u32;
+    let min_y = (center.1 - radius).floor().max(0.0) as u32;
+    let max_x = ((center.0 + radius).ceil().max(0.0) as u32).min(width);
+    let max_y = ((center.1 + radius).ceil().max(0.0) as u32).min(height);
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let (dx, dy) = (x as f64 + 0.5 - center.0, y as f64 + 0.5 - center.1);
+            if dx * dx + dy * dy <= radius * radius {
+                image.put_pixel(x, y, Rgb(color));
+            }
+        }
+    }
+}
+
+/// Draws `points` of the `config` box as discs stretched over the image.
+fn draw_points(image: &mut RgbImage, points: &[(f64, f64)], config: (f64, f64), color: [u8; 3]) {
+    let (width, height) = image.dimensions();
+    let radius = (width.max(height) as f64 / 200.0).max(2.0);
+    for &(x, y) in points {
+        let center = (
+            x / config.0 * width as f64,
+            (1.0 - y / config.1) * height as f64,
+        );
+        draw_disc(image, center, radius, color);
+    }
+}
+
+/// Renders black points on a white background, `POINTS_SIZE` pixels along the longest side.
+pub fn render_points(points: &[(f64, f64)], config: (f64, f64)) -> RgbImage {
+    let scale = POINTS_SIZE as f64 / config.0.max(config.1);
+    let width = ((config.0 * scale).round() as u32).max(1);
+    let height = ((config.1 * scale).round() as u32).max(1);
+    let mut image = RgbImage::from_pixel(width, height, Rgb([255, 255, 255]));
+    draw_points(&mut image, points, config, [0, 0, 0]);
+    image
+}
+
+/// Renders the label grid, one image pixel per label, with the seeds in black. Pixels outside
+/// the domain, labeled 0, are white.
+pub fn render_cells(
+    pixels: &[usize],
+    reso: (usize, usize),
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    colors: &[[u8; 3]],
+) -> Result<RgbImage, &'static str> {
+    let (reso_x, reso_y) = reso;
+    if pixels.len() != reso_x * reso_y || pixels.is_empty() {
+        return Err("Label grid size doesn't match the resolution");
+    }
+    if pixels.iter().any(|&label| label > colors.len()) {
+        return Err("Label grid contains pixels without a valid seed");
+    }
+    let mut image = RgbImage::from_fn(reso_x as u32, reso_y as u32, |x, y| {
+        match pixels[x as usize + (reso_y - 1 - y as usize) * reso_x] {
+            0 => Rgb([255, 255, 255]),
+            label => Rgb(colors[label - 1]),
+        }
+    });
+    draw_points(&mut image, points, config, [0, 0, 0]);
+    Ok(image)
+}
+
+/// Renders the distances with the Viridis color map, from 0 to the largest one, with the seeds
+/// in white. Pixels without a distance are white.
+pub fn render_distance(
+    field: &DistanceField,
+    points: &[(f64, f64)],
+    config: (f64, f64),
+) -> RgbImage {
+    let (reso_x, reso_y) = field.reso;
+    let max = field
+        .distances
+        .iter()
+        .filter(|d| !d.is_nan())
+        .fold(0.0_f64, |max, &d| max.max(d));
+    let mut image = RgbImage::from_fn(reso_x as u32, reso_y as u32, |x, y| {
+        let distance = field.distances[x as usize + (reso_y - 1 - y as usize) * reso_x];
+        match distance {
+            d if d.is_nan() => Rgb([255, 255, 255]),
+            d => Rgb(viridis(if max > 0.0 { d / max } else { 0.0 })),
+        }
+    });
+    draw_points(&mut image, points, config, [255, 255, 255]);
+    image
+}
+
+fn svg_header(svg: &mut String, config: (f64, f64)) {
+    let scale = POINTS_SIZE as f64 / config.0.max(config.1);
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+        (config.0 * scale).round(),
+        (config.1 * scale).round(),
+        config.0,
+        config.1
+    )
+    .unwrap();
+    // Flip the y axis so that the box origin is at the bottom left
+    writeln!(svg, r#"<g transform="matrix(1 0 0 -1 0 {})">"#, config.1).unwrap();
+}
+
+fn svg_points(svg: &mut String, points: &[(f64, f64)], config: (f64, f64), color: &str) {
+    let radius = config.0.max(config.1) / 200.0;
+    for (x, y) in points {
+        writeln!(
+            svg,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+            x, y, radius, color
+        )
+        .unwrap();
+    }
+}
+
+/// Draws black points on a white background.
+pub fn draw_points_svg(points: &[(f64, f64)], config: (f64, f64)) -> String {
+    let mut svg = String::new();
+    svg_header(&mut svg, config);
+    writeln!(
+        svg,
+        r#"<rect width="{}" height="{}" fill="white"/>"#,
+        config.0, config.1
+    )
+    .unwrap();
+    svg_points(&mut svg, points, config, "black");
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+/// Draws the pieces of each cell as paths, holes included, with the seeds in black.
+pub fn draw_cells_svg(
+    pieces: &[Vec<Polygon>],
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    colors: &[[u8; 3]],
+) -> String {
+    let mut svg = String::new();
+    svg_header(&mut svg, config);
+    for (cell_pieces, color) in pieces.iter().zip(colors) {
+        for piece in cell_pieces {
+            let path: Vec<String> = std::iter::once(&piece.exterior)
+                .chain(&piece.holes)
+                .map(|ring| {
+                    let vertices: Vec<String> =
+                        ring.iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+                    format!("M{}Z", vertices.join("L"))
+                })
+                .collect();
+            writeln!(
+                svg,
+                r##"<path d="{}" fill="#{:02x}{:02x}{:02x}" fill-rule="evenodd" stroke="black" stroke-width="{}"/>"##,
+                path.join(""),
+                color[0],
+                color[1],
+                color[2],
+                config.0.max(config.1) / 1000.0
+            )
+            .unwrap();
+        }
+    }
+    svg_points(&mut svg, points, config, "black");
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_cells() {
+        // Left and right halves, the bottom left pixel is outside the domain
+        let reso = (4, 2);
+        let pixels = vec![0, 1, 2, 2, 1, 1, 2, 2];
+        let colors = vec![[10, 20, 30], [40, 50, 60]];
+
+        let image = render_cells(&pixels, reso, &[], (4.0, 2.0), &colors).unwrap();
+
+        // The first row of the image is the top of the box
+        assert_eq!(image.get_pixel(0, 0), &Rgb([10, 20, 30]));
+        assert_eq!(image.get_pixel(3, 0), &Rgb([40, 50, 60]));
+        assert_eq!(image.get_pixel(0, 1), &Rgb([255, 255, 255]));
+        assert_eq!(viridis(0.0), VIRIDIS[0]);
+        assert_eq!(viridis(1.0), VIRIDIS[8]);
+
+        // An empty grid, such as the one of `-j none`, isn't rendered
+        assert!(render_cells(&[], reso, &[], (4.0, 2.0), &colors).is_err());
+    }
+}
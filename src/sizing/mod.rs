@@ -0,0 +1,115 @@
+// Sizing fields giving the minimum distance between points as a function of position.
+
+use std::cell::Cell;
+use std::path::Path;
+use std::str::FromStr;
+
+use rand::Rng;
+
+use crate::domain::Polygon;
+use crate::mode3;
+
+/// Minimum distance around a point, in domain units.
+pub type SizingFn = Box<dyn Fn((f64, f64)) -> f64>;
+
+/// Parses an analytic expression of `x` and `y`, such as `0.5 + 0.1 * x`.
+pub fn expression(expr: &str) -> Result<SizingFn, &'static str> {
+    let expr = meval::Expr::from_str(expr).map_err(|_| "Unable to parse the sizing expression")?;
+    let sizing = expr
+        .bind2("x", "y")
+        .map_err(|_| "The sizing expression may only use the variables x and y")?;
+    Ok(Box::new(move |(x, y)| sizing(x, y)))
+}
+
+/// Reads a grayscale image stretched over the `config` box, black pixels mapping to the first
+/// distance of `range` and white pixels to the second.
+///
+/// The first row of the image is the top of the box.
+pub fn image(path: &Path, config: (f64, f64), range: (f64, f64)) -> Result<SizingFn, &'static str> {
+    let image = image::open(path)
+        .map_err(|_| "Unable to read the sizing image")?
+        .into_luma16();
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return Err("The sizing image is empty");
+    }
+
+    Ok(Box::new(move |(x, y)| {
+        let px = ((x / config.0 * width as f64).max(0.0) as u32).min(width - 1);
+        let py = (((1.0 - y / config.1) * height as f64).max(0.0) as u32).min(height - 1);
+        let value = image.get_pixel(px, py).0[0] as f64 / u16::MAX as f64;
+        range.0 + value * (range.1 - range.0)
+    }))
+}
+
+/// Poisson disk sampling of the `width * height` box, with a minimum distance of
+/// `sizing(point)` clamped to `range` around each point. The box wraps around when `periodic`,
+/// and candidates outside `domain` are rejected.
+///
+/// Fails if `sizing` isn't positive and finite at any of the sampled positions.
+pub fn generate_points<R: Rng, F: Fn((f64, f64)) -> f64>(
+    sizing: F,
+    range: (f64, f64),
+    width: f64,
+    height: f64,
+    periodic: bool,
+    domain: Option<&Polygon>,
+    rng: &mut R,
+) -> Result<Vec<(f64, f64)>, &'static str> {
+    if !(range.0 > 0.0 && range.0 <= range.1) {
+        return Err("The distance range must be positive and ordered");
+    }
+    let invalid = Cell::new(false);
+    let checked_sizing = |p| {
+        let distance = sizing(p);
+        if !(distance.is_finite() && distance > 0.0) {
+            invalid.set(true);
+        }
+        distance
+    };
+    let points = mode3::generate_points_with_sizing(
+        checked_sizing,
+        range,
+        width,
+        height,
+        periodic,
+        domain,
+        rng,
+    );
+    if invalid.get() {
+        return Err("The sizing field must be positive and finite over the box");
+    }
+    Ok(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_expression() {
+        let sizing = expression("0.5 + 0.1 * x + y^2").unwrap();
+        assert!((sizing((2.0, 3.0)) - 9.7).abs() < 1e-12);
+
+        assert!(expression("0.5 + z").is_err());
+        assert!(expression("0.5 +").is_err());
+    }
+
+    #[test]
+    fn test_invalid_sizing() {
+        // NaN for x < 5
+        let sizing = expression("sqrt(x - 5)").unwrap();
+        let points = generate_points(
+            sizing,
+            (0.5, 2.0),
+            10.0,
+            10.0,
+            false,
+            None,
+            &mut StdRng::seed_from_u64(42),
+        );
+        assert!(points.is_err());
+    }
+}
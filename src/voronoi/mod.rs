@@ -0,0 +1,261 @@
+// Exact Voronoi diagram, used as a reference for the JFA approximation.
+//
+// Each cell starts as the box and is clipped by the bisector of its seed and every seed close
+// enough to cut it. Seeds are bucketed on a grid and visited ring by ring, so that the search
+// stops as soon as no remaining seed can be closer than twice the cell radius.
+
+use crate::cells::{VertexIndex, VoronoiCells};
+
+/// Clips the convex `polygon` to the half-plane of the points closer to `seed` than to `other`.
+fn clip(polygon: &[(f64, f64)], seed: (f64, f64), other: (f64, f64)) -> Vec<(f64, f64)> {
+    let normal = (other.0 - seed.0, other.1 - seed.1);
+    let middle = ((seed.0 + other.0) / 2.0, (seed.1 + other.1) / 2.0);
+    let side = |p: (f64, f64)| (p.0 - middle.0) * normal.0 + (p.1 - middle.1) * normal.1;
+
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (i, &current) in polygon.iter().enumerate() {
+        let next = polygon[(i + 1) % polygon.len()];
+        let (side_current, side_next) = (side(current), side(next));
+
+        if side_current <= 0.0 {
+            clipped.push(current);
+        }
+        // The edge crosses the bisector
+        if (side_current < 0.0 && side_next > 0.0) || (side_current > 0.0 && side_next < 0.0) {
+            let t = side_current / (side_current - side_next);
+            clipped.push((
+                current.0 + t * (next.0 - current.0),
+                current.1 + t * (next.1 - current.1),
+            ));
+        }
+    }
+
+    clipped
+}
+
+fn distance(pa: (f64, f64), pb: (f64, f64)) -> f64 {
+    let dx = pa.0 - pb.0;
+    let dy = pa.1 - pb.1;
+    (dx * dx + dy * dy).sqrt()
+}
+
+/// Seeds bucketed on a grid covering the box, visited ring by ring around a bucket.
+pub(crate) struct SeedGrid {
+    pub bucket_size: f64,
+    grid_width: usize,
+    grid_height: usize,
+    buckets: Vec<Vec<usize>>,
+}
+
+impl SeedGrid {
+    pub fn new(points: &[(f64, f64)], config: (f64, f64)) -> Self {
+        // About one seed per bucket
+        let bucket_size = (config.0 * config.1 / points.len() as f64).sqrt();
+        let grid_width = (config.0 / bucket_size).ceil().max(1.0) as usize;
+        let grid_height = (config.1 / bucket_size).ceil().max(1.0) as usize;
+
+        let mut grid = SeedGrid {
+            bucket_size,
+            grid_width,
+            grid_height,
+            buckets: vec![Vec::new(); grid_width * grid_height],
+        };
+        for (i, &point) in points.iter().enumerate() {
+            let (x, y) = grid.bucket_of(point);
+            grid.buckets[x + y * grid_width].push(i);
+        }
+        grid
+    }
+
+    pub fn bucket_of(&self, p: (f64, f64)) -> (usize, usize) {
+        let x = ((p.0 / self.bucket_size).max(0.0) as usize).min(self.grid_width - 1);
+        let y = ((p.1 / self.bucket_size).max(0.0) as usize).min(self.grid_height - 1);
+        (x, y)
+    }
+
+    /// Returns the number of rings needed to cover the grid from any bucket.
+    pub fn num_rings(&self) -> usize {
+        self.grid_width.max(self.grid_height)
+    }
+
+    /// Returns the seeds in the buckets `ring` buckets away from `(bx, by)`.
+    pub fn ring(&self, (bx, by): (usize, usize), ring: usize) -> impl Iterator<Item = usize> + '_ {
+        let (x_min, x_max) = (bx as isize - ring as isize, bx as isize + ring as isize);
+        let (y_min, y_max) = (by as isize - ring as isize, by as isize + ring as isize);
+        (y_min..=y_max)
+            .flat_map(move |y| (x_min..=x_max).map(move |x| (x, y)))
+            .filter(move |&(x, y)| {
+                let on_ring = x == x_min || x == x_max || y == y_min || y == y_max;
+                on_ring
+                    && x >= 0
+                    && y >= 0
+                    && x < self.grid_width as isize
+                    && y < self.grid_height as isize
+            })
+            .flat_map(move |(x, y)| self.buckets[x as usize + y as usize * self.grid_width].iter())
+            .copied()
+    }
+}
+
+/// Computes the exact Voronoi cells of `points`, clipped to the `(0..config.0, 0..config.1)` box.
+pub fn voronoi(points: &[(f64, f64)], config: (f64, f64)) -> Result<VoronoiCells, &'static str> {
+    if points.is_empty() {
+        return Err("No points to build the Voronoi diagram from");
+    }
+
+    let seeds = SeedGrid::new(points, config);
+
+    let mut vertices = VertexIndex::new(1e-9 * config.0.max(config.1));
+    let mut cells = Vec::with_capacity(points.len());
+
+    for (i, &seed) in points.iter().enumerate() {
+        let mut polygon = vec![(0.0, 0.0), (config.0, 0.0), config, (0.0, config.1)];
+        let bucket = seeds.bucket_of(seed);
+
+        for ring in 0..seeds.num_rings() {
+            // Seeds past this ring are at least `ring * bucket_size` away, and can only cut the
+            // cell if they are closer than twice its radius
+            let radius = polygon
+                .iter()
+                .map(|&vertex| distance(seed, vertex))
+                .fold(0.0, f64::max);
+            if ring > 1 && (ring - 1) as f64 * seeds.bucket_size > 2.0 * radius {
+                break;
+            }
+
+            for j in seeds.ring(bucket, ring) {
+                if j != i && points[j] != seed {
+                    polygon = clip(&polygon, seed, points[j]);
+                }
+            }
+        }
+
+        // Share vertices between cells, merging those closer than the tolerance
+        let mut cell: Vec<usize> = Vec::with_capacity(polygon.len());
+        for vertex in polygon {
+            let index = vertices.insert(vertex);
+            // Degenerate edges collapse when several bisectors meet at a vertex
+            if cell.last() != Some(&index) && cell.first() != Some(&index) {
+                cell.push(index);
+            }
+        }
+        if cell.len() < 3 {
+            cell.clear();
+        }
+        cells.push(cell);
+    }
+
+    Ok(VoronoiCells {
+        vertices: vertices.vertices,
+        cells,
+        period: None,
+    })
+}
+
+/// Labels each pixel with the cell containing its center, `i + 1` for the seed `i`, matching the
+/// layout of the JFA backends.
+pub fn rasterize(cells: &VoronoiCells, reso: (usize, usize), config: (f64, f64)) -> Vec<usize> {
+    let (reso_x, reso_y) = reso;
+    let pixel_size = (config.0 / reso_x as f64, config.1 / reso_y as f64);
+    let mut pixel_grid = vec![0; reso_x * reso_y];
+
+    for i in 0..cells.cells.len() {
+        let polygon = cells.polygon(i);
+        let n = polygon.len();
+        if n == 0 {
+            continue;
+        }
+
+        // Spans are slightly widened so that pixel centers on a shared edge aren't lost to
+        // rounding, the last cell drawn wins the tie
+        let margin = 1e-9 * pixel_size.0.max(pixel_size.1);
+        let y_min = polygon.iter().map(|p| p.1).fold(f64::MAX, f64::min) - margin;
+        let y_max = polygon.iter().map(|p| p.1).fold(f64::MIN, f64::max) + margin;
+        let row_min = ((y_min / pixel_size.1 - 0.5).ceil().max(0.0)) as usize;
+        let row_max = ((y_max / pixel_size.1 - 0.5)
+            .floor()
+            .min(reso_y as f64 - 1.0)) as isize;
+
+        for y in row_min as isize..=row_max {
+            // The cell is convex, so each row crosses it on a single span
+            let center_y = (y as f64 + 0.5) * pixel_size.1;
+            let (mut x_min, mut x_max) = (f64::MAX, f64::MIN);
+            for j in 0..n {
+                let (a, b) = (polygon[j], polygon[(j + 1) % n]);
+                let (low, high) = (a.1.min(b.1) - margin, a.1.max(b.1) + margin);
+                if low <= center_y && center_y <= high {
+                    let x = if a.1 == b.1 {
+                        a.0
+                    } else {
+                        let t = ((center_y - a.1) / (b.1 - a.1)).clamp(0.0, 1.0);
+                        a.0 + t * (b.0 - a.0)
+                    };
+                    x_min = x_min.min(x);
+                    x_max = x_max.max(x);
+                }
+            }
+            if x_min > x_max {
+                continue;
+            }
+            let (x_min, x_max) = (x_min - margin, x_max + margin);
+
+            let col_min = ((x_min / pixel_size.0 - 0.5).ceil().max(0.0)) as usize;
+            let col_max = ((x_max / pixel_size.0 - 0.5)
+                .floor()
+                .min(reso_x as f64 - 1.0)) as isize;
+            for x in col_min as isize..=col_max {
+                pixel_grid[x as usize + y as usize * reso_x] = i + 1;
+            }
+        }
+    }
+
+    pixel_grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_cells() {
+        let points = vec![(2.5, 2.5), (7.5, 2.5), (2.5, 7.5), (7.5, 7.5), (5.0, 5.0)];
+        let config = (10.0, 10.0);
+
+        let cells = voronoi(&points, config).unwrap();
+
+        // The middle seed gets a diamond, the corner seeds a pentagon
+        assert_eq!(cells.cells[4].len(), 4);
+        assert!((cells.area(4) - 12.5).abs() < 1e-9);
+        for i in 0..4 {
+            assert_eq!(cells.cells[i].len(), 5);
+            assert!(cells.area(i) > 0.0);
+        }
+        let total: f64 = (0..points.len()).map(|i| cells.area(i)).sum();
+        assert!((total - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rasterize_matches_nearest_seed() {
+        let points = vec![(1.0, 1.0), (3.5, 2.0), (8.0, 7.5), (2.0, 9.0), (6.0, 4.0)];
+        let config = (10.0, 10.0);
+        let reso = (64, 48);
+
+        let cells = voronoi(&points, config).unwrap();
+        let pixel_grid = rasterize(&cells, reso, config);
+
+        for y in 0..reso.1 {
+            for x in 0..reso.0 {
+                let center = (
+                    (x as f64 + 0.5) * config.0 / reso.0 as f64,
+                    (y as f64 + 0.5) * config.1 / reso.1 as f64,
+                );
+                let nearest = (0..points.len())
+                    .min_by(|&a, &b| {
+                        distance(points[a], center).total_cmp(&distance(points[b], center))
+                    })
+                    .unwrap();
+                assert_eq!(pixel_grid[x + y * reso.0], nearest + 1);
+            }
+        }
+    }
+}
@@ -0,0 +1,475 @@
+// Unstructured meshes written for ParaView, as legacy VTK or XML VTU files.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use geo::TriangulateEarcut;
+
+use crate::cells::{signed_area, VertexIndex, VoronoiCells};
+use crate::delaunay::{Side, Triangulation};
+use crate::domain::Polygon;
+
+/// VTK cell types.
+const VTK_VERTEX: u8 = 1;
+const VTK_TRIANGLE: u8 = 5;
+const VTK_POLYGON: u8 = 7;
+
+/// Values attached to each cell.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CellData {
+    Int(Vec<i64>),
+    Float(Vec<f64>),
+}
+
+/// Cells of any number of vertices in the `z = 0` plane, with named cell data.
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub points: Vec<(f64, f64)>,
+    /// Point indices of each cell: vertices, triangles or counterclockwise polygons
+    pub cells: Vec<Vec<usize>>,
+    pub cell_data: Vec<(String, CellData)>,
+    /// Edges along the box, with the side they lie on
+    pub boundary_edges: Vec<(usize, usize, Side)>,
+}
+
+/// Returns, for each cell, the number of other owners sharing an edge with its owner, cells
+/// split into several pieces having the same owner.
+fn neighbor_counts(cells: &[Vec<usize>], owners: &[usize]) -> Vec<i64> {
+    let mut edges: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (cell, &owner) in cells.iter().zip(owners) {
+        for (j, &a) in cell.iter().enumerate() {
+            let b = cell[(j + 1) % cell.len()];
+            edges.entry((a.min(b), a.max(b))).or_default().push(owner);
+        }
+    }
+
+    let mut neighbors: HashMap<usize, Vec<usize>> = HashMap::new();
+    for sharing in edges.values() {
+        for &i in sharing {
+            let owner_neighbors = neighbors.entry(i).or_default();
+            owner_neighbors.extend(sharing.iter().filter(|&&j| j != i));
+        }
+    }
+    let counts: HashMap<usize, i64> = neighbors
+        .into_iter()
+        .map(|(owner, mut owner_neighbors)| {
+            owner_neighbors.sort_unstable();
+            owner_neighbors.dedup();
+            (owner, owner_neighbors.len() as i64)
+        })
+        .collect();
+    owners.iter().map(|owner| counts[owner]).collect()
+}
+
+/// Returns the edges used by a single cell, with the side of the box around `points` they are the
+/// closest to.
+fn boundary_edges(cells: &[Vec<usize>], points: &[(f64, f64)]) -> Vec<(usize, usize, Side)> {
+    let mut uses: HashMap<(usize, usize), usize> = HashMap::new();
+    for cell in cells {
+        for (j, &a) in cell.iter().enumerate() {
+            let b = cell[(j + 1) % cell.len()];
+            *uses.entry((a.min(b), a.max(b))).or_default() += 1;
+        }
+    }
+
+    let (min, max) = points.iter().fold(
+        ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN)),
+        |(min, max), &(x, y)| ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y))),
+    );
+    let mut edges = Vec::new();
+    for cell in cells {
+        for (j, &a) in cell.iter().enumerate() {
+            let b = cell[(j + 1) % cell.len()];
+            if uses[&(a.min(b), a.max(b))] != 1 {
+                continue;
+            }
+            let (x, y) = (
+                (points[a].0 + points[b].0) / 2.0,
+                (points[a].1 + points[b].1) / 2.0,
+            );
+            let side = [
+                (y - min.1, Side::Bottom),
+                (max.0 - x, Side::Right),
+                (max.1 - y, Side::Top),
+                (x - min.0, Side::Left),
+            ]
+            .into_iter()
+            .min_by(|p, q| p.0.total_cmp(&q.0))
+            .unwrap()
+            .1;
+            edges.push((a, b, side));
+        }
+    }
+    edges
+}
+
+/// Returns whether the counterclockwise `polygon` has no reflex vertex.
+fn is_convex(polygon: &[(f64, f64)]) -> bool {
+    let n = polygon.len();
+    (0..n).all(|i| {
+        let (a, b, c) = (polygon[i], polygon[(i + 1) % n], polygon[(i + 2) % n]);
+        (b.0 - a.0) * (c.1 - b.1) - (b.1 - a.1) * (c.0 - b.0) >= 0.0
+    })
+}
+
+impl Mesh {
+    /// One vertex cell per point, with its seed index.
+    pub fn from_points(points: &[(f64, f64)]) -> Mesh {
+        Mesh {
+            points: points.to_vec(),
+            cells: (0..points.len()).map(|i| vec![i]).collect(),
+            cell_data: vec![(
+                "seed".to_string(),
+                CellData::Int((0..points.len() as i64).collect()),
+            )],
+            boundary_edges: Vec::new(),
+        }
+    }
+
+    /// One polygon per Voronoi cell with its area, neighbor count and seed index. Seeds without
+    /// a polygon are skipped.
+    ///
+    /// In a periodic box, each polygon gets its own unwrapped copy of its vertices, neighbors
+    /// being still counted across the edges of the box, which then has no boundary edges.
+    pub fn from_voronoi(voronoi: &VoronoiCells) -> Mesh {
+        let seeds: Vec<usize> = (0..voronoi.cells.len())
+            .filter(|&i| voronoi.cells[i].len() >= 3)
+            .collect();
+        let shared: Vec<Vec<usize>> = seeds.iter().map(|&i| voronoi.cells[i].clone()).collect();
+
+        let (points, cells) = match voronoi.period {
+            None => (voronoi.vertices.clone(), shared.clone()),
+            Some(_) => {
+                let mut points = Vec::new();
+                let mut cells = Vec::with_capacity(seeds.len());
+                for &i in &seeds {
+                    let polygon = voronoi.polygon(i);
+                    cells.push((points.len()..points.len() + polygon.len()).collect());
+                    points.extend(polygon);
+                }
+                (points, cells)
+            }
+        };
+
+        // In a periodic box all edges are shared
+        let boundary = match voronoi.period {
+            None => boundary_edges(&shared, &voronoi.vertices),
+            Some(_) => Vec::new(),
+        };
+
+        Mesh {
+            points,
+            cells,
+            boundary_edges: boundary,
+            cell_data: vec![
+                (
+                    "area".to_string(),
+                    CellData::Float(seeds.iter().map(|&i| voronoi.area(i)).collect()),
+                ),
+                (
+                    "neighbors".to_string(),
+                    CellData::Int(neighbor_counts(&shared, &seeds)),
+                ),
+                (
+                    "seed".to_string(),
+                    CellData::Int(seeds.iter().map(|&i| i as i64).collect()),
+                ),
+            ],
+        }
+    }
+
+    /// One polygon per piece of the Voronoi cells clipped to a domain, as returned by
+    /// `domain::clip_cells`, with its area, the neighbor count of its cell and its seed index.
+    ///
+    /// Pieces sharing an edge are merged on their vertices. Pieces with holes or reflex vertices
+    /// are split into triangles, so that every polygon is convex.
+    pub fn from_clipped(pieces: &[Vec<Polygon>]) -> Mesh {
+        let (min, max) = pieces
+            .iter()
+            .flatten()
+            .flat_map(|piece| &piece.exterior)
+            .fold(
+                ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN)),
+                |(min, max), &(x, y)| ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y))),
+            );
+        // Clipping moves shared vertices by a tiny amount, which depends on the cell
+        let mut vertices = VertexIndex::new(1e-6 * (max.0 - min.0).max(max.1 - min.1));
+
+        let mut cells: Vec<Vec<usize>> = Vec::new();
+        let mut seeds: Vec<usize> = Vec::new();
+        let mut areas: Vec<f64> = Vec::new();
+        let mut add_cell = |polygon: &[(f64, f64)], seed: usize| {
+            let mut cell: Vec<usize> = Vec::with_capacity(polygon.len());
+            for &vertex in polygon {
+                let index = vertices.insert(vertex);
+                if cell.last() != Some(&index) && cell.first() != Some(&index) {
+                    cell.push(index);
+                }
+            }
+            if cell.len() >= 3 {
+                cells.push(cell);
+                seeds.push(seed);
+                areas.push(signed_area(polygon));
+            }
+        };
+        for (seed, cell_pieces) in pieces.iter().enumerate() {
+            for piece in cell_pieces {
+                if piece.holes.is_empty() && is_convex(&piece.exterior) {
+                    add_cell(&piece.exterior, seed);
+                    continue;
+                }
+                for triangle in piece.to_geo().earcut_triangles() {
+                    let [a, b, c] = triangle.to_array().map(|p| (p.x, p.y));
+                    // Triangles are counterclockwise, like the other cells
+                    if signed_area(&[a, b, c]) < 0.0 {
+                        add_cell(&[a, c, b], seed);
+                    } else {
+                        add_cell(&[a, b, c], seed);
+                    }
+                }
+            }
+        }
+
+        let points = vertices.vertices;
+        Mesh {
+            boundary_edges: boundary_edges(&cells, &points),
+            cell_data: vec![
+                ("area".to_string(), CellData::Float(areas)),
+                (
+                    "neighbors".to_string(),
+                    CellData::Int(neighbor_counts(&cells, &seeds)),
+                ),
+                (
+                    "seed".to_string(),
+                    CellData::Int(seeds.iter().map(|&i| i as i64).collect()),
+                ),
+            ],
+            points,
+            cells,
+        }
+    }
+
+    /// One triangle per Delaunay triangle with its area and neighbor count.
+    pub fn from_triangulation(triangulation: &Triangulation) -> Mesh {
+        let cells: Vec<Vec<usize>> = triangulation.triangles.iter().map(|t| t.to_vec()).collect();
+        let owners: Vec<usize> = (0..cells.len()).collect();
+        let areas = cells
+            .iter()
+            .map(|cell| {
+                let polygon: Vec<(f64, f64)> =
+                    cell.iter().map(|&v| triangulation.vertices[v]).collect();
+                signed_area(&polygon)
+            })
+            .collect();
+
+        Mesh {
+            points: triangulation.vertices.clone(),
+            cell_data: vec![
+                ("area".to_string(), CellData::Float(areas)),
+                (
+                    "neighbors".to_string(),
+                    CellData::Int(neighbor_counts(&cells, &owners)),
+                ),
+            ],
+            cells,
+            boundary_edges: triangulation.boundary_edges.clone(),
+        }
+    }
+
+    fn cell_type(cell: &[usize]) -> u8 {
+        match cell.len() {
+            1 => VTK_VERTEX,
+            3 => VTK_TRIANGLE,
+            _ => VTK_POLYGON,
+        }
+    }
+
+    /// Writes an ASCII legacy VTK file.
+    pub fn write_legacy(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "# vtk DataFile Version 3.0")?;
+        writeln!(file, "blue_noise mesh")?;
+        writeln!(file, "ASCII")?;
+        writeln!(file, "DATASET UNSTRUCTURED_GRID")?;
+
+        writeln!(file, "POINTS {} double", self.points.len())?;
+        for (x, y) in &self.points {
+            writeln!(file, "{} {} 0", x, y)?;
+        }
+
+        let size: usize = self.cells.iter().map(|cell| cell.len() + 1).sum();
+        writeln!(file, "CELLS {} {}", self.cells.len(), size)?;
+        for cell in &self.cells {
+            write!(file, "{}", cell.len())?;
+            for v in cell {
+                write!(file, " {}", v)?;
+            }
+            writeln!(file)?;
+        }
+        writeln!(file, "CELL_TYPES {}", self.cells.len())?;
+        for cell in &self.cells {
+            writeln!(file, "{}", Mesh::cell_type(cell))?;
+        }
+
+        if !self.cell_data.is_empty() {
+            writeln!(file, "CELL_DATA {}", self.cells.len())?;
+        }
+        for (name, data) in &self.cell_data {
+            match data {
+                CellData::Int(values) => {
+                    writeln!(file, "SCALARS {} int 1", name)?;
+                    writeln!(file, "LOOKUP_TABLE default")?;
+                    for value in values {
+                        writeln!(file, "{}", value)?;
+                    }
+                }
+                CellData::Float(values) => {
+                    writeln!(file, "SCALARS {} double 1", name)?;
+                    writeln!(file, "LOOKUP_TABLE default")?;
+                    for value in values {
+                        writeln!(file, "{}", value)?;
+                    }
+                }
+            }
+        }
+        file.flush()
+    }
+
+    /// Writes an ASCII XML VTU file.
+    pub fn write_vtu(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, r#"<?xml version="1.0"?>"#)?;
+        writeln!(
+            file,
+            r#"<VTKFile type="UnstructuredGrid" version="0.1" byte_order="LittleEndian">"#
+        )?;
+        writeln!(file, "<UnstructuredGrid>")?;
+        writeln!(
+            file,
+            r#"<Piece NumberOfPoints="{}" NumberOfCells="{}">"#,
+            self.points.len(),
+            self.cells.len()
+        )?;
+
+        writeln!(file, "<Points>")?;
+        writeln!(
+            file,
+            r#"<DataArray type="Float64" NumberOfComponents="3" format="ascii">"#
+        )?;
+        for (x, y) in &self.points {
+            writeln!(file, "{} {} 0", x, y)?;
+        }
+        writeln!(file, "</DataArray>")?;
+        writeln!(file, "</Points>")?;
+
+        writeln!(file, "<Cells>")?;
+        writeln!(
+            file,
+            r#"<DataArray type="Int64" Name="connectivity" format="ascii">"#
+        )?;
+        for cell in &self.cells {
+            let line: Vec<String> = cell.iter().map(|v| v.to_string()).collect();
+            writeln!(file, "{}", line.join(" "))?;
+        }
+        writeln!(file, "</DataArray>")?;
+        writeln!(
+            file,
+            r#"<DataArray type="Int64" Name="offsets" format="ascii">"#
+        )?;
+        let mut offset = 0;
+        for cell in &self.cells {
+            offset += cell.len();
+            writeln!(file, "{}", offset)?;
+        }
+        writeln!(file, "</DataArray>")?;
+        writeln!(
+            file,
+            r#"<DataArray type="UInt8" Name="types" format="ascii">"#
+        )?;
+        for cell in &self.cells {
+            writeln!(file, "{}", Mesh::cell_type(cell))?;
+        }
+        writeln!(file, "</DataArray>")?;
+        writeln!(file, "</Cells>")?;
+
+        writeln!(file, "<CellData>")?;
+        for (name, data) in &self.cell_data {
+            let (kind, values): (&str, Vec<String>) = match data {
+                CellData::Int(values) => ("Int64", values.iter().map(|v| v.to_string()).collect()),
+                CellData::Float(values) => {
+                    ("Float64", values.iter().map(|v| v.to_string()).collect())
+                }
+            };
+            writeln!(
+                file,
+                r#"<DataArray type="{}" Name="{}" format="ascii">"#,
+                kind, name
+            )?;
+            writeln!(file, "{}", values.join(" "))?;
+            writeln!(file, "</DataArray>")?;
+        }
+        writeln!(file, "</CellData>")?;
+
+        writeln!(file, "</Piece>")?;
+        writeln!(file, "</UnstructuredGrid>")?;
+        writeln!(file, "</VTKFile>")?;
+        file.flush()
+    }
+
+    /// Writes an XML VTU file when `path` ends with `.vtu`, and a legacy VTK file otherwise.
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        let xml = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("vtu"));
+        if xml {
+            self.write_vtu(path)
+        } else {
+            self.write_legacy(path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_voronoi_mesh() {
+        // Two unit squares side by side, and a seed without a polygon
+        let voronoi = VoronoiCells {
+            vertices: vec![
+                (0.0, 0.0),
+                (1.0, 0.0),
+                (2.0, 0.0),
+                (0.0, 1.0),
+                (1.0, 1.0),
+                (2.0, 1.0),
+            ],
+            cells: vec![vec![0, 1, 4, 3], vec![], vec![1, 2, 5, 4]],
+            period: None,
+        };
+
+        let mesh = Mesh::from_voronoi(&voronoi);
+
+        assert_eq!(mesh.cells.len(), 2);
+        assert_eq!(mesh.boundary_edges.len(), 6);
+        assert!(mesh.boundary_edges.contains(&(2, 5, Side::Right)));
+        assert_eq!(
+            mesh.cell_data,
+            vec![
+                ("area".to_string(), CellData::Float(vec![1.0, 1.0])),
+                ("neighbors".to_string(), CellData::Int(vec![1, 1])),
+                ("seed".to_string(), CellData::Int(vec![0, 2])),
+            ]
+        );
+
+        let path = std::env::temp_dir().join("blue_noise_test_mesh.vtk");
+        mesh.write(&path).unwrap();
+        let legacy = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(legacy.contains("CELLS 2 10\n4 0 1 4 3\n4 1 2 5 4\n"));
+        assert!(legacy.contains("CELL_TYPES 2\n7\n7\n"));
+    }
+}